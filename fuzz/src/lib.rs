@@ -0,0 +1,120 @@
+//! Fuzz targets for the board, in the layout `cargo fuzz` expects. Run one with e.g.
+//!
+//! ```sh
+//! cargo +nightly fuzz run read_fen
+//! ```
+//!
+//! from the repository root. The checks live here rather than in the targets, so they can be
+//! replayed on a crashing input with a plain `cargo run` or a debugger. `gunnir-board` is built
+//! with `debug-invariants`, so every `make_move` also checks the incremental state.
+
+use gunnir_board::*;
+
+const FORMATS: [FenFormat; 3] = [FenFormat::Standard, FenFormat::Shredder, FenFormat::XFen];
+
+/// A valid board picked by `selector`: the start position, Kiwipete or one of the positions
+/// from `perft960.txt`.
+fn board(selector: u16) -> Board {
+    let positions = include_str!("../../gunnir-board/perft960.txt");
+    match selector % 962 {
+        0 => Board::start_pos(),
+        1 => {
+            Board::read_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap()
+        }
+        n => {
+            let line = positions.lines().nth(n as usize - 2).unwrap();
+            Board::read_fen(line.split('\t').nth(1).unwrap().trim()).unwrap()
+        }
+    }
+}
+
+fn legal_moves(board: &Board) -> Vec<Move> {
+    let mut moves = vec![];
+    board.gen_moves(|m| moves.extend(m));
+    moves
+}
+
+fn check(board: &Board) {
+    if let Err(violations) = board.check_invariants() {
+        panic!("{} is invalid: {violations:?}", board.fen(true));
+    }
+}
+
+/// `read_fen` must return `None` for anything it can't read, and a valid board otherwise.
+pub fn read_fen(data: &[u8]) {
+    if let Ok(fen) = str::from_utf8(data)
+        && let Some(board) = Board::read_fen(fen)
+    {
+        check(&board);
+        legal_moves(&board);
+    }
+}
+
+/// Every board read from a FEN must be written back to a FEN that reads as the same board.
+/// Standard FEN can't always tell which rook castling rights belong to, so that only has to
+/// round-trip the FEN itself.
+pub fn fen_round_trip(data: &[u8]) {
+    let Some(board) = str::from_utf8(data).ok().and_then(Board::read_fen) else {
+        return;
+    };
+
+    for format in FORMATS {
+        let fen = board.fen_with(format);
+        let read = Board::read_fen(&fen).unwrap_or_else(|| panic!("failed to read back {fen}"));
+        assert_eq!(read.fen_with(format), fen);
+        if format != FenFormat::Standard {
+            assert_eq!(read.hash, board.hash, "{fen}");
+        }
+    }
+}
+
+/// `parse_move` must never panic, and any legal move it returns must be written back to
+/// something that parses to the same move.
+pub fn parse_move(data: &[u8]) {
+    let [a, b, rest @ ..] = data else {
+        return;
+    };
+    let board = board(u16::from_le_bytes([*a, *b]));
+    let Ok(lan) = str::from_utf8(rest) else {
+        return;
+    };
+
+    let legal = legal_moves(&board);
+    for chess960 in [false, true] {
+        if let Some(mov) = board.parse_move(lan, chess960)
+            && legal.contains(&mov)
+        {
+            let uci = board.move_to_uci(mov, chess960);
+            assert_eq!(board.parse_move(&uci, chess960), Some(mov), "{uci}");
+        }
+    }
+}
+
+/// Plays the moves picked by the bytes and checks all incrementally updated state after every
+/// move, including the hash, against the same board read from its FEN.
+pub fn move_sequence(data: &[u8]) {
+    let [a, b, rest @ ..] = data else {
+        return;
+    };
+    let mut board = board(u16::from_le_bytes([*a, *b]));
+
+    for &byte in rest {
+        let moves = legal_moves(&board);
+        if moves.is_empty() || board.halfmove_clock >= 99 {
+            return;
+        }
+
+        let mov = moves[byte as usize % moves.len()];
+        board.make_move(mov);
+        check(&board);
+
+        let read = Board::read_fen(&board.fen(true)).unwrap();
+        assert_eq!(read.hash, board.hash, "{}", board.fen(true));
+        assert_eq!(read.pawn_hash, board.pawn_hash, "{}", board.fen(true));
+        assert_eq!(
+            Board::unpack(&board.pack()).map(|b| b.hash),
+            Some(board.hash)
+        );
+    }
+}
@@ -475,34 +475,6 @@ mod bmi2 {
     }
 }
 
-#[cfg(feature = "perft-all-960")]
-fn generate_perft960_tests() {
-    println!("cargo::rerun-if-changed=perft960.txt");
-    let results = fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/perft960.txt")).unwrap();
-    let file_path = Path::new(&env::var("OUT_DIR").unwrap()).join("perft_generated.rs");
-    let mut writer = BufWriter::new(fs::File::create(file_path).unwrap());
-
-    for line in results.lines() {
-        let mut parts = line.split('\t');
-        let number = parts.next().unwrap().trim();
-        let fen = parts.next().unwrap().trim();
-
-        let expected: Vec<_> = parts.map(|s| s.trim()).collect();
-        let expected = expected.join(", ");
-
-        writeln!(
-            writer,
-            "
-        perft_test!(
-            perft960_p{number}: {fen:?};
-            {expected},
-        );
-        "
-        )
-        .unwrap();
-    }
-}
-
 fn main() {
     println!("cargo::rerun-if-changed=build.rs");
     println!("cargo::rerun-if-env-changed=CARGO_CFG_TARGET_FEATURE");
@@ -516,7 +488,4 @@ fn main() {
     } else {
         magic::generate(&mut writer).unwrap();
     }
-
-    #[cfg(feature = "perft-all-960")]
-    generate_perft960_tests();
 }
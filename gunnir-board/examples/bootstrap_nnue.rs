@@ -0,0 +1,61 @@
+//! Writes a network that reproduces the material and piece-square tables, averaged over the
+//! game phase. Until we have trained a real network, this is the one we ship.
+//!
+//! Usage: `cargo run -p gunnir-board --example bootstrap_nnue [output]`
+
+use std::{env, fs};
+
+use gunnir_board::*;
+
+/// The center of the clipping range. Around it, the difference of the squares of two neurons
+/// `(CENTER + d)^2 - (CENTER - d)^2 = 4 * CENTER * d` is linear in `d`.
+const CENTER: i16 = 128;
+/// With this output weight, one unit of `d` is about one centipawn.
+const OUTPUT_WEIGHT: i16 = 10;
+
+fn main() {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "gunnir-board/nets/default.nnue".to_string());
+
+    // Each pair of neurons carries an equal share of every piece's value, so that no single
+    // neuron leaves the linear range in realistic positions.
+    let pairs = HIDDEN as i32 / 2;
+    let mut net = Network::zeroed();
+
+    for &perspective in Color::ALL {
+        for &color in Color::ALL {
+            for &pt in PieceType::ALL {
+                for &sq in Square::ALL {
+                    // Seen from its owner's side of the board, so that black's pieces are
+                    // worth the same as white's.
+                    let own_sq = match color {
+                        Color::White => sq,
+                        Color::Black => Square::from_idx(sq.idx() ^ 56),
+                    };
+                    let score = psqt(own_sq, pt, Color::White);
+                    let value = (score.mg + score.eg) / 2;
+                    let value = if color == perspective { value } else { -value };
+
+                    let row = &mut net.feature_weights[feature(perspective, sq, pt, color)];
+                    for k in 0..pairs {
+                        let share = value.div_euclid(pairs) + (k < value.rem_euclid(pairs)) as i32;
+                        row[2 * k as usize] = share as i16;
+                        row[2 * k as usize + 1] = -share as i16;
+                    }
+                }
+            }
+        }
+    }
+
+    net.feature_bias = [CENTER; HIDDEN];
+    for k in 0..HIDDEN / 2 {
+        net.output_weights[0][2 * k] = OUTPUT_WEIGHT;
+        net.output_weights[0][2 * k + 1] = -OUTPUT_WEIGHT;
+        net.output_weights[1][2 * k] = -OUTPUT_WEIGHT;
+        net.output_weights[1][2 * k + 1] = OUTPUT_WEIGHT;
+    }
+
+    fs::write(&path, net.to_bytes()).expect("Failed to write network");
+    println!("Wrote {path}");
+}
@@ -13,6 +13,20 @@ pub struct CastlingRights {
     pub long: Option<File>,
 }
 
+/// How castling rights are written in FEN. `Board::read_fen` accepts all of them.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum FenFormat {
+    /// `KQkq`, which is ambiguous in Chess960 if there are two rooks on the same side of the
+    /// king.
+    #[default]
+    Standard,
+    /// The files of the castling rooks, like `HAha`.
+    Shredder,
+    /// `KQkq` if the castling rook is the outermost rook on its side of the king, and its file
+    /// otherwise.
+    XFen,
+}
+
 #[derive(Clone, Copy)]
 pub struct Board {
     /// For each piece type we store a bitboard of all pieces of that type. Note that these bitboards
@@ -46,6 +60,11 @@ pub struct Board {
     pub stm: Color,
     /// Zobrist hash of the board
     pub hash: u64,
+    /// Zobrist hash of only the pawns on the board, used to cache pawn structure evaluation.
+    pub pawn_hash: u64,
+    /// Sum of the material and piece-square values of all pieces from white's point of view.
+    /// Like `hash`, this is updated incrementally whenever a piece is added or removed.
+    pub psqt: Score,
 }
 
 impl Board {
@@ -86,15 +105,26 @@ impl Board {
     }
 
     #[inline]
-    fn toggle_square(&mut self, sq: Square, color: Color, pt: PieceType) {
+    pub(crate) fn toggle_square(&mut self, sq: Square, color: Color, pt: PieceType) {
         self.pieces[pt] ^= sq;
         self.occupied[color] ^= sq;
 
         self.hash ^= ZOBRIST.piece(sq, pt, color);
+        if pt == PieceType::Pawn {
+            self.pawn_hash ^= ZOBRIST.piece(sq, pt, color);
+        }
+
+        // We can't use `occupied` to tell whether we added or removed the piece, since in
+        // Chess960 the king and rook may briefly share a square while castling.
+        if self.pieces[pt].contains(sq) {
+            self.psqt += psqt(sq, pt, color);
+        } else {
+            self.psqt -= psqt(sq, pt, color);
+        }
     }
 
     #[inline]
-    fn set_en_passant(&mut self, f: Option<File>) {
+    pub(crate) fn set_en_passant(&mut self, f: Option<File>) {
         if let Some(old) = mem::replace(&mut self.en_passant, f) {
             self.hash ^= ZOBRIST.en_passant(old);
         }
@@ -104,7 +134,7 @@ impl Board {
     }
 
     #[inline]
-    fn set_castles(&mut self, color: Color, castles: Option<File>, short: bool) {
+    pub(crate) fn set_castles(&mut self, color: Color, castles: Option<File>, short: bool) {
         let rights = if short {
             &mut self.castles[color].short
         } else {
@@ -121,7 +151,13 @@ impl Board {
     }
 
     /// Makes a move on the current board. Assumes the move is legal for the current position.
+    #[inline]
     pub fn make_move(&mut self, mov: Move) {
+        self.make_move_with(mov, &mut ());
+    }
+
+    /// Like `make_move`, but reports every piece the move adds, removes or moves to `observer`.
+    pub fn make_move_with<O: MoveObserver>(&mut self, mov: Move, observer: &mut O) {
         let (from, to, flag, promotion) = (
             mov.from(),
             mov.to(),
@@ -157,6 +193,7 @@ impl Board {
             // We must update the victim's bitboard. The mailbox itself will be updated automatically
             // once the new piece moves to the square.
             self.toggle_square(to, !self.stm, victim);
+            observer.piece_removed(to, victim, !self.stm);
 
             // If we take a rook, we must check if it still has its castling rights, and remove them.
             let their_back_rank = Rank::R8.relative_to(self.stm);
@@ -176,6 +213,7 @@ impl Board {
             MoveFlag::None => {
                 self.toggle_square(from, self.stm, piece);
                 self.toggle_square(to, self.stm, piece);
+                observer.piece_moved(from, to, piece, self.stm);
 
                 self.mailbox[from] = None;
                 self.mailbox[to] = Some(piece);
@@ -197,32 +235,28 @@ impl Board {
                             }
                         }
                     }
-                    PieceType::Pawn => {
-                        // If we double-push, we must update the en passant file.
-                        if to.rank().idx().abs_diff(from.rank().idx()) == 2 {
-                            debug_assert_eq!(to.file(), from.file());
-                            debug_assert_eq!(from.rank(), Rank::R2.relative_to(self.stm));
-
-                            // We actually only care about the en passant file if the opponent
-                            // has a pawn that may take our pushed pawn on their next turn.
-                            // Otherwise, just omitting the en-passant file cannot have an
-                            // effect on the game state. This way, we only include the en-passant
-                            // information in the zobrist hash when it is relevant, hopefully
-                            // increasing the accuracy of any kinds of transposition tables
-                            // using the hash.
-                            // Note that this may result in a technically incorrect FEN string
-                            // being printed, but it can't affect the next legal moves so we don't
-                            // care.
-                            if self
-                                .colored_pieces(PieceType::Pawn, !self.stm)
-                                .intersect(pawn_attacks(
-                                    from.offset(0, self.stm.signum()),
-                                    self.stm,
-                                ))
-                                .is_non_empty()
-                            {
-                                self.set_en_passant(Some(to.file()));
-                            }
+                    // If we double-push, we must update the en passant file.
+                    PieceType::Pawn if to.rank().idx().abs_diff(from.rank().idx()) == 2 => {
+                        debug_assert_eq!(to.file(), from.file());
+                        debug_assert_eq!(from.rank(), Rank::R2.relative_to(self.stm));
+
+                        // We actually only care about the en passant file if the opponent
+                        // has a pawn that may take our pushed pawn on their next turn.
+                        // Otherwise, just omitting the en-passant file cannot have an
+                        // effect on the game state. This way, we only include the en-passant
+                        // information in the zobrist hash when it is relevant, hopefully
+                        // increasing the accuracy of any kinds of transposition tables
+                        // using the hash.
+                        // Note that this may result in a technically incorrect FEN string
+                        // being printed, but it can't affect the next legal moves so we don't
+                        // care. The `strict-en-passant` feature additionally requires the
+                        // capture to be legal, see `normalize_en_passant`.
+                        if self
+                            .colored_pieces(PieceType::Pawn, !self.stm)
+                            .intersect(pawn_attacks(from.offset(0, self.stm.signum()), self.stm))
+                            .is_non_empty()
+                        {
+                            self.set_en_passant(Some(to.file()));
                         }
                     }
                     _ => {}
@@ -253,6 +287,8 @@ impl Board {
                 self.toggle_square(to, self.stm, PieceType::King);
                 self.toggle_square(rook_from, self.stm, PieceType::Rook);
                 self.toggle_square(rook_to, self.stm, PieceType::Rook);
+                observer.piece_moved(from, to, PieceType::King, self.stm);
+                observer.piece_moved(rook_from, rook_to, PieceType::Rook, self.stm);
 
                 // Update the mailbox. We first clear both, then set both. This is to ensure
                 // that even if one piece starts on the square that the other moves to, it
@@ -276,11 +312,13 @@ impl Board {
 
                 self.toggle_square(from, self.stm, PieceType::Pawn);
                 self.toggle_square(to, self.stm, PieceType::Pawn);
+                observer.piece_moved(from, to, PieceType::Pawn, self.stm);
 
                 self.mailbox[from] = None;
                 self.mailbox[to] = Some(PieceType::Pawn);
 
                 self.toggle_square(target_square, !self.stm, PieceType::Pawn);
+                observer.piece_removed(target_square, PieceType::Pawn, !self.stm);
                 self.mailbox[target_square] = None;
             }
             MoveFlag::Promotion => {
@@ -289,6 +327,8 @@ impl Board {
 
                 self.toggle_square(from, self.stm, PieceType::Pawn);
                 self.toggle_square(to, self.stm, promotion);
+                observer.piece_removed(from, PieceType::Pawn, self.stm);
+                observer.piece_added(to, promotion, self.stm);
                 self.mailbox[from] = None;
                 self.mailbox[to] = Some(promotion);
             }
@@ -297,6 +337,64 @@ impl Board {
         self.stm = !self.stm;
         self.hash ^= ZOBRIST.black_to_move;
         self.calc_pinned_and_checkers();
+        self.normalize_en_passant();
+
+        if cfg!(feature = "debug-invariants")
+            && let Err(violations) = self.check_invariants()
+        {
+            panic!(
+                "{mov:?} left the board in an invalid state: {violations:?}\n{}",
+                self.fen(true)
+            );
+        }
+    }
+
+    /// With the `strict-en-passant` feature, clears the en passant file unless the side to move
+    /// can actually take en passant, so that identical positions always have the same hash and
+    /// FEN. Must be called after `calc_pinned_and_checkers`.
+    #[inline]
+    pub(crate) fn normalize_en_passant(&mut self) {
+        if cfg!(feature = "strict-en-passant")
+            && self.en_passant.is_some()
+            && !self.has_legal_en_passant()
+        {
+            self.set_en_passant(None);
+        }
+    }
+
+    /// Passes the turn to the opponent without moving any piece. This is never legal in an actual
+    /// game, but the search uses it for null move pruning. Must not be called while in check.
+    pub fn make_null_move(&mut self) {
+        debug_assert!(self.checkers.is_empty(), "Null move while in check");
+        debug_assert!(self.halfmove_clock < 100);
+
+        self.halfmove_clock += 1;
+        self.fullmove_count += (self.stm == Color::Black) as u32;
+
+        self.set_en_passant(None);
+
+        self.stm = !self.stm;
+        self.hash ^= ZOBRIST.black_to_move;
+        self.calc_pinned_and_checkers();
+    }
+
+    /// A board without any pieces, with white to move. Not a valid position on its own.
+    pub(crate) fn empty() -> Self {
+        Board {
+            pieces: Default::default(),
+            mailbox: Default::default(),
+            occupied: Default::default(),
+            castles: Default::default(),
+            en_passant: None,
+            pinned: Bitboard::EMPTY,
+            checkers: Bitboard::EMPTY,
+            halfmove_clock: 0,
+            fullmove_count: 0,
+            stm: Color::White,
+            hash: 0,
+            pawn_hash: 0,
+            psqt: Score::ZERO,
+        }
     }
 
     pub fn start_pos() -> Self {
@@ -316,19 +414,7 @@ impl Board {
             return None;
         }
 
-        let mut board = Board {
-            pieces: Default::default(),
-            mailbox: Default::default(),
-            occupied: Default::default(),
-            castles: Default::default(),
-            en_passant: None,
-            pinned: Bitboard::EMPTY,
-            checkers: Bitboard::EMPTY,
-            halfmove_clock: 0,
-            fullmove_count: 0,
-            stm: Color::White,
-            hash: 0,
-        };
+        let mut builder = BoardBuilder::new();
 
         let mut rank = 8u8;
         for line in pieces.split('/') {
@@ -340,13 +426,13 @@ impl Board {
             let mut file = 0;
             let chars = line.bytes();
             for ch in chars {
+                if file >= 8 {
+                    return None;
+                }
                 if matches!(ch, b'1'..=b'7') {
                     file += ch - b'0';
                     continue;
                 }
-                if file >= 8 {
-                    return None;
-                }
 
                 let pt = match ch.to_ascii_lowercase() {
                     b'p' => PieceType::Pawn,
@@ -360,99 +446,98 @@ impl Board {
                 let color = Color::from_idx(ch.is_ascii_lowercase() as u8);
 
                 let sq = Square::from_file_rank(File::from_idx(file), Rank::from_idx(rank));
-                board.toggle_square(sq, color, pt);
-                if board.mailbox[sq].replace(pt).is_some() {
+                if builder.put(sq, color, pt).is_some() {
                     return None;
                 }
 
                 file += 1;
             }
-        }
-
-        // Sanity check that both sides have a king.
-        if board.colored_pieces(PieceType::King, Color::White).popcnt() != 1
-            || board.colored_pieces(PieceType::King, Color::Black).popcnt() != 1
-        {
-            return None;
+            if file != 8 {
+                return None;
+            }
         }
 
         if rank != 0 {
             return None;
         }
 
-        board.stm = match stm {
+        builder.set_side_to_move(match stm {
             "w" => Color::White,
             "b" => Color::Black,
             _ => return None,
-        };
-
-        if board.stm == Color::Black {
-            board.hash ^= ZOBRIST.black_to_move;
-        }
+        });
 
         if castles != "-" {
             for ch in castles.bytes() {
                 let color = Color::from_idx(ch.is_ascii_lowercase() as u8);
-                let king = board.king(color);
+                let king = builder.king(color)?;
+                let is_rook = |f: File| {
+                    builder.piece_on(Square::from_file_rank(f, king.rank()))
+                        == Some((color, PieceType::Rook))
+                };
 
                 let file = match ch.to_ascii_lowercase() {
                     b'a'..=b'h' => File::from_idx(ch.to_ascii_lowercase() - b'a'),
-                    b'k' => (king.file().idx()..8).map(File::from_idx).find(|&f| {
-                        board
-                            .colored_pieces(PieceType::Rook, color)
-                            .contains(Square::from_file_rank(f, king.rank()))
-                    })?,
-                    b'q' => (0..king.file().idx())
+                    // Like X-FEN, we take `K` and `Q` to mean the outermost rook, which is the
+                    // only possible one in classical chess.
+                    b'k' => (king.file().idx()..8)
                         .rev()
                         .map(File::from_idx)
-                        .find(|&f| {
-                            board
-                                .colored_pieces(PieceType::Rook, color)
-                                .contains(Square::from_file_rank(f, king.rank()))
-                        })?,
+                        .find(|&f| is_rook(f))?,
+                    b'q' => (0..king.file().idx())
+                        .map(File::from_idx)
+                        .find(|&f| is_rook(f))?,
                     _ => return None,
                 };
 
-                let king_sq = board.king(color);
-                let rook_sq = Square::from_file_rank(file, king_sq.rank());
-                if !board
-                    .colored_pieces(PieceType::Rook, color)
-                    .contains(rook_sq)
-                {
+                if !is_rook(file) {
                     return None;
                 }
 
-                board.set_castles(color, Some(file), file > king_sq.file());
+                builder.set_castling(color, file > king.file(), Some(file));
             }
         }
 
         if epts != "-" {
-            let mut chars = epts.bytes();
-            let file = match chars.next()? {
-                ch @ b'a'..=b'h' => File::from_idx(ch - b'a'),
-                _ => return None,
-            };
-
-            if !matches!(chars.next(), Some(b'3' | b'6')) {
+            let sq = Square::parse(epts)?;
+            if sq.rank() != Rank::R3.relative_to(!builder.side_to_move()) {
                 return None;
             }
 
-            board.set_en_passant(Some(file));
+            builder.set_en_passant(Some(sq.file()));
         }
 
-        board.halfmove_clock = hmc.parse().ok()?;
-        if board.halfmove_clock >= 100 {
-            return None;
-        }
+        builder.set_halfmove_clock(hmc.parse().ok()?);
+        builder.set_fullmove_count(fmc.parse().ok()?);
 
-        board.fullmove_count = fmc.parse().ok()?;
+        // Checks the kings, clocks and castling and en passant state against the pieces.
+        builder.build()
+    }
 
-        board.calc_pinned_and_checkers();
+    /// Writes the board in FEN, with castling rights in Shredder-FEN if `chess960` is set.
+    pub fn fen(&self, chess960: bool) -> String {
+        self.fen_with(if chess960 {
+            FenFormat::Shredder
+        } else {
+            FenFormat::Standard
+        })
+    }
 
-        Some(board)
+    /// Whether there's no other rook of `color` on its first rank further away from the king
+    /// than the one on `file`.
+    fn is_outermost_rook(&self, color: Color, file: File, short: bool) -> bool {
+        let files = if short {
+            file.idx() + 1..8
+        } else {
+            0..file.idx()
+        };
+        let rank = Rank::R1.relative_to(color);
+        files.map(File::from_idx).all(|f| {
+            self.colored_piece_on(Square::from_file_rank(f, rank), color) != Some(PieceType::Rook)
+        })
     }
 
-    pub fn fen(&self, chess960: bool) -> String {
+    pub fn fen_with(&self, format: FenFormat) -> String {
         use std::fmt::Write;
         let mut res = String::new();
 
@@ -481,21 +566,25 @@ impl Board {
         write!(res, "{:?} ", self.stm).unwrap();
 
         let mut castles = String::new();
-        if let Some(f) = self.castles[Color::White].short {
-            let ch = if !chess960 { 'K' } else { f.to_char() };
-            castles.push(ch);
-        }
-        if let Some(f) = self.castles[Color::White].long {
-            let ch = if !chess960 { 'Q' } else { f.to_char() };
-            castles.push(ch);
-        }
-        if let Some(f) = self.castles[Color::Black].short {
-            let ch = if !chess960 { 'K' } else { f.to_char() };
-            castles.push(ch.to_ascii_lowercase());
-        }
-        if let Some(f) = self.castles[Color::Black].long {
-            let ch = if !chess960 { 'Q' } else { f.to_char() };
-            castles.push(ch.to_ascii_lowercase());
+        for color in Color::ALL.iter().copied() {
+            let rights = self.castles[color];
+            for (file, short) in [(rights.short, true), (rights.long, false)] {
+                let Some(f) = file else {
+                    continue;
+                };
+
+                let side = if short { 'K' } else { 'Q' };
+                let ch = match format {
+                    FenFormat::Standard => side,
+                    FenFormat::Shredder => f.to_char(),
+                    FenFormat::XFen if self.is_outermost_rook(color, f, short) => side,
+                    FenFormat::XFen => f.to_char(),
+                };
+                castles.push(match color {
+                    Color::White => ch,
+                    Color::Black => ch.to_ascii_lowercase(),
+                });
+            }
         }
 
         if castles.is_empty() {
@@ -555,14 +644,20 @@ impl Board {
 
     #[inline]
     pub fn parse_move(&self, lan: &str, chess960: bool) -> Option<Move> {
-        if !(4..=5).contains(&lan.len()) {
+        if !(4..=5).contains(&lan.len()) || !lan.is_ascii() {
             return None;
         }
 
         let from = Square::parse(&lan[..2])?;
         let to = Square::parse(&lan[2..4])?;
+        if from == to {
+            return None;
+        }
         let promote_to = match lan.as_bytes().get(4) {
-            Some(&b) => Some(PieceType::from_char(b as char)?),
+            Some(&b) => Some(
+                PieceType::from_char(b as char)
+                    .filter(|pt| !matches!(pt, PieceType::Pawn | PieceType::King))?,
+            ),
             None => None,
         };
 
@@ -610,4 +705,232 @@ impl Board {
             },
         ))
     }
+
+    /// Formats `mov` in long algebraic notation, the inverse of `parse_move`. In Chess960 mode,
+    /// castling moves are written as the king capturing its own rook.
+    pub fn move_to_uci(&self, mov: Move, chess960: bool) -> String {
+        let (from, mut to) = (mov.from(), mov.to());
+
+        if chess960 && mov.move_flag() == MoveFlag::Castle {
+            let castles = self.castles[self.stm];
+            let rook_file = if to.file() == File::G {
+                castles.short
+            } else {
+                castles.long
+            };
+            if let Some(file) = rook_file {
+                to = Square::from_file_rank(file, to.rank());
+            }
+        }
+
+        let mut uci = format!("{from:#?}{to:#?}");
+        if let Some(pt) = mov.promotes_to() {
+            uci.push(pt.to_char(Color::Black));
+        }
+        uci
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+    const FORMATS: [FenFormat; 3] = [FenFormat::Standard, FenFormat::Shredder, FenFormat::XFen];
+
+    #[test]
+    fn castling_formats() {
+        // White may castle with the inner rook on the queen side, black with the outer one.
+        let board = Board::read_fen("r1r1k2r/8/8/8/8/8/8/RR2K2R w BHah - 0 1").unwrap();
+
+        let fens: Vec<_> = FORMATS.iter().map(|&f| board.fen_with(f)).collect();
+        assert_eq!(
+            fens,
+            [
+                "r1r1k2r/8/8/8/8/8/8/RR2K2R w KQkq - 0 1",
+                "r1r1k2r/8/8/8/8/8/8/RR2K2R w HBha - 0 1",
+                "r1r1k2r/8/8/8/8/8/8/RR2K2R w KBkq - 0 1",
+            ]
+        );
+
+        // Standard FEN loses which rook white may castle with, the others don't.
+        for fen in &fens[1..] {
+            assert_eq!(Board::read_fen(fen).unwrap().hash, board.hash);
+        }
+        let standard = Board::read_fen(&fens[0]).unwrap();
+        assert_eq!(standard.castles[Color::White].long, Some(File::A));
+        assert_eq!(standard.castles[Color::Black].long, Some(File::A));
+    }
+
+    #[test]
+    fn round_trip_perft960() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+
+        for line in include_str!("../perft960.txt").lines() {
+            let mut board = Board::read_fen(line.split('\t').nth(1).unwrap().trim()).unwrap();
+
+            // Random moves shuffle the rooks around, so some castling rooks become inner ones.
+            for _ in 0..30 {
+                let shredder = board.fen_with(FenFormat::Shredder);
+                for format in [FenFormat::Shredder, FenFormat::XFen] {
+                    let read = Board::read_fen(&board.fen_with(format)).unwrap();
+                    assert_eq!(read.fen_with(FenFormat::Shredder), shredder);
+                    assert_eq!(read.hash, board.hash);
+                }
+
+                let mut moves = vec![];
+                board.gen_moves(|m| moves.extend(m));
+                if moves.is_empty() {
+                    break;
+                }
+                board.make_move(moves[rng.random_range(0..moves.len())]);
+            }
+        }
+    }
+
+    #[test]
+    fn uci_moves() {
+        let board = Board::read_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        for (lan, lan960) in [("e1g1", "e1h1"), ("e1c1", "e1a1"), ("b7a8n", "b7a8n")] {
+            let mov = board.parse_move(lan, false).unwrap();
+            assert_eq!(board.move_to_uci(mov, false), lan);
+            assert_eq!(board.move_to_uci(mov, true), lan960);
+        }
+
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+        for line in include_str!("../perft960.txt").lines().step_by(4) {
+            let mut board = Board::read_fen(line.split('\t').nth(1).unwrap().trim()).unwrap();
+
+            for _ in 0..30 {
+                let mut moves = vec![];
+                board.gen_moves(|m| moves.extend(m));
+                if moves.is_empty() {
+                    break;
+                }
+                for &mov in &moves {
+                    let lan = board.move_to_uci(mov, true);
+                    assert_eq!(board.parse_move(&lan, true), Some(mov), "{lan}");
+                }
+                board.make_move(moves[rng.random_range(0..moves.len())]);
+            }
+        }
+    }
+
+    #[test]
+    fn read_fen_rejects_invalid() {
+        for fen in [
+            // Ranks that are too short or too long.
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR1 w KQkq - 0 1",
+            "4k3/8/77/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8//8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/77777777777777777777777777777777777777777/8/8/8/8/4K3 w - - 0 1",
+            // Pawns on the first or last rank.
+            "4k2P/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/p3K3 w - - 0 1",
+            // The side not to move is in check.
+            "4k3/8/8/8/8/8/4R3/4K3 w - - 0 1",
+            // Castling rights without a king on the back rank.
+            "4k3/8/8/8/8/8/4K3/R6R w AH - 0 1",
+            "4k3/8/8/8/8/8/4K3/R6R w K - 0 1",
+            // En passant squares on the wrong rank, or without a pawn that just moved.
+            "4k3/8/8/8/3pP3/8/8/4K3 b - e6 0 1",
+            "4k3/8/8/8/3p4/8/8/4K3 b - e3 0 1",
+            "4k3/8/8/8/3pP3/8/4P3/4K3 b - e3 0 1",
+            "4k3/8/8/8/3pP3/8/8/4K3 b - e3x 0 1",
+        ] {
+            assert!(Board::read_fen(fen).is_none(), "{fen}");
+        }
+    }
+
+    #[test]
+    fn parse_move_rejects_invalid() {
+        let board = Board::read_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        for lan in [
+            "e1e1", "b7b8k", "b7b8p", "b7b8x", "é1e2", "e1é2", "e1e", "e1e2e3",
+        ] {
+            for chess960 in [false, true] {
+                assert_eq!(board.parse_move(lan, chess960), None, "{lan}");
+            }
+        }
+    }
+
+    #[test]
+    fn read_fen_mutations() {
+        // A cheap stand-in for the fuzz targets: corrupting valid FENs must never panic, and
+        // anything that is still accepted must be a valid board.
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+        const ALPHABET: &[u8] = b"pnbrqkPNBRQK12345678/ wb-abcdefghABCDEFGH0123456789";
+
+        for line in include_str!("../perft960.txt").lines().step_by(4) {
+            let fen = line.split('\t').nth(1).unwrap().trim().as_bytes();
+            for _ in 0..50 {
+                let mut bytes = fen.to_vec();
+                for _ in 0..rng.random_range(1..4) {
+                    let i = rng.random_range(0..bytes.len());
+                    match rng.random_range(0..3) {
+                        0 => bytes[i] = ALPHABET[rng.random_range(0..ALPHABET.len())],
+                        1 => drop(bytes.remove(i)),
+                        _ => bytes.insert(i, ALPHABET[rng.random_range(0..ALPHABET.len())]),
+                    }
+                }
+
+                let fen = String::from_utf8(bytes).unwrap();
+                if let Some(board) = Board::read_fen(&fen) {
+                    assert_eq!(board.check_invariants(), Ok(()), "{fen}");
+                    let mut moves = vec![];
+                    board.gen_moves(|m| moves.extend(m));
+                    for mov in moves {
+                        let mut child = board;
+                        child.make_move(mov);
+                        assert_eq!(child.check_invariants(), Ok(()), "{fen} {mov:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn legal_en_passant() {
+        for (fen, legal) in [
+            ("8/6k1/8/8/3pP3/8/8/4K3 b - e3 0 1", true),
+            // Taking would expose the king to the rook along the rank.
+            ("7k/8/8/KPp4r/8/8/8/8 w - c6 0 2", false),
+            // The capturing pawn is pinned by the bishop.
+            ("8/6k1/8/8/3pP3/8/8/B3K3 b - e3 0 1", false),
+            // `read_fen` doesn't require a capturing pawn.
+            ("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1", false),
+        ] {
+            let board = Board::read_fen(fen).unwrap();
+            let strict = cfg!(feature = "strict-en-passant");
+            assert_eq!(board.en_passant.is_some(), legal || !strict, "{fen}");
+            assert_eq!(board.has_legal_en_passant(), legal, "{fen}");
+
+            let mut ep_moves = 0;
+            board.gen_moves(|m| {
+                ep_moves += m
+                    .into_iter()
+                    .filter(|m| m.move_flag() == MoveFlag::EnPassant)
+                    .count()
+            });
+            assert_eq!(ep_moves > 0, legal, "{fen}");
+        }
+    }
+
+    #[test]
+    fn strict_en_passant() {
+        let mut board = Board::read_fen("7k/2p5/8/KP5r/8/8/8/8 b - - 0 1").unwrap();
+        board.make_move(board.parse_move("c7c5", false).unwrap());
+        let same = Board::read_fen("7k/8/8/KPp4r/8/8/8/8 w - - 0 2").unwrap();
+
+        if cfg!(feature = "strict-en-passant") {
+            assert_eq!(board.en_passant, None);
+            assert_eq!(board.hash, same.hash);
+            assert_eq!(board.fen(false), same.fen(false));
+        } else {
+            // White's pawn attacks the skipped square, which is all we check by default.
+            assert_eq!(board.en_passant, Some(File::C));
+            assert_ne!(board.hash, same.hash);
+        }
+    }
 }
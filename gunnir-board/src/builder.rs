@@ -0,0 +1,267 @@
+use enum_map::EnumMap;
+
+use crate::*;
+
+/// A position that is being set up piece by piece, like in a board editor. Unlike a `Board`, it
+/// may be invalid while it is edited; `build` checks it and computes everything derived from it.
+#[derive(Clone, Debug)]
+pub struct BoardBuilder {
+    pieces: EnumMap<Square, Option<(Color, PieceType)>>,
+    stm: Color,
+    castles: EnumMap<Color, CastlingRights>,
+    en_passant: Option<File>,
+    halfmove_clock: u8,
+    fullmove_count: u32,
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoardBuilder {
+    /// An empty board with white to move and no castling rights.
+    pub fn new() -> Self {
+        Self {
+            pieces: EnumMap::default(),
+            stm: Color::White,
+            castles: EnumMap::default(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_count: 1,
+        }
+    }
+
+    pub fn piece_on(&self, sq: Square) -> Option<(Color, PieceType)> {
+        self.pieces[sq]
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.stm
+    }
+
+    pub fn castling(&self, color: Color) -> CastlingRights {
+        self.castles[color]
+    }
+
+    pub fn en_passant(&self) -> Option<File> {
+        self.en_passant
+    }
+
+    /// Puts a piece on `sq`, and returns the piece that was there before.
+    pub fn put(&mut self, sq: Square, color: Color, pt: PieceType) -> Option<(Color, PieceType)> {
+        self.pieces[sq].replace((color, pt))
+    }
+
+    /// Removes the piece on `sq`, if any, and returns it.
+    pub fn remove(&mut self, sq: Square) -> Option<(Color, PieceType)> {
+        self.pieces[sq].take()
+    }
+
+    pub fn set_side_to_move(&mut self, color: Color) {
+        self.stm = color;
+    }
+
+    /// Lets `color` castle with the rook on `file` of its first rank, on the short side if
+    /// `short` is set and on the long side otherwise. `None` removes the right.
+    pub fn set_castling(&mut self, color: Color, short: bool, file: Option<File>) {
+        if short {
+            self.castles[color].short = file;
+        } else {
+            self.castles[color].long = file;
+        }
+    }
+
+    /// Sets the file of a pawn that just moved two squares and may be taken en passant.
+    pub fn set_en_passant(&mut self, file: Option<File>) {
+        self.en_passant = file;
+    }
+
+    pub fn set_halfmove_clock(&mut self, halfmove_clock: u8) {
+        self.halfmove_clock = halfmove_clock;
+    }
+
+    pub fn set_fullmove_count(&mut self, fullmove_count: u32) {
+        self.fullmove_count = fullmove_count;
+    }
+
+    /// The square of `color`'s king, or of one of them if there are several.
+    pub(crate) fn king(&self, color: Color) -> Option<Square> {
+        Square::ALL
+            .iter()
+            .copied()
+            .find(|&sq| self.pieces[sq] == Some((color, PieceType::King)))
+    }
+
+    /// Builds the board, or returns `None` if it isn't a position movegen can deal with. Both
+    /// sides need exactly one king and at most 16 pieces, there may be no pawns on the first or
+    /// last rank, the side not to move may not be in check, and castling rights and the en
+    /// passant file need their rooks and pawn in place. The halfmove clock must be below 100.
+    pub fn build(&self) -> Option<Board> {
+        let mut board = Board::empty();
+        for sq in Square::ALL.iter().copied() {
+            if let Some((color, pt)) = self.pieces[sq] {
+                board.toggle_square(sq, color, pt);
+                board.mailbox[sq] = Some(pt);
+            }
+        }
+
+        // Computing pins and checks needs the kings.
+        if board.colored_pieces(PieceType::King, Color::White).popcnt() != 1
+            || board.colored_pieces(PieceType::King, Color::Black).popcnt() != 1
+        {
+            return None;
+        }
+
+        // More can't be reached in a game, and wouldn't fit into `Board::pack`.
+        if Color::ALL.iter().any(|&c| board.occupied[c].popcnt() > 16) {
+            return None;
+        }
+
+        let back_ranks = Rank::R1.bitboard() | Rank::R8.bitboard();
+        if (board.pieces[PieceType::Pawn] & back_ranks).is_non_empty() {
+            return None;
+        }
+
+        board.stm = self.stm;
+        if board.stm == Color::Black {
+            board.hash ^= ZOBRIST.black_to_move;
+        }
+
+        for color in Color::ALL.iter().copied() {
+            board.set_castles(color, self.castles[color].short, true);
+            board.set_castles(color, self.castles[color].long, false);
+        }
+        board.set_en_passant(self.en_passant);
+
+        if self.halfmove_clock >= 100 {
+            return None;
+        }
+        board.halfmove_clock = self.halfmove_clock;
+        board.fullmove_count = self.fullmove_count;
+
+        board.calc_pinned_and_checkers();
+        board.normalize_en_passant();
+
+        // This also rejects everything movegen can't deal with, like the side not to move being
+        // in check, or an en passant square without a pawn that could have just moved there.
+        board.check_invariants().ok()?;
+
+        Some(board)
+    }
+}
+
+impl Board {
+    /// A builder with this position, to edit it from.
+    pub fn to_builder(&self) -> BoardBuilder {
+        let mut builder = BoardBuilder::new();
+        for color in Color::ALL.iter().copied() {
+            for sq in self.occupied[color] {
+                builder.put(sq, color, self.mailbox[sq].unwrap());
+            }
+        }
+
+        builder.stm = self.stm;
+        builder.castles = self.castles;
+        builder.en_passant = self.en_passant;
+        builder.halfmove_clock = self.halfmove_clock;
+        builder.fullmove_count = self.fullmove_count;
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for line in include_str!("../perft960.txt").lines().step_by(8) {
+            let board = Board::read_fen(line.split('\t').nth(1).unwrap().trim()).unwrap();
+            let built = board.to_builder().build().unwrap();
+            assert_eq!(built.fen(true), board.fen(true));
+            assert_eq!(built.hash, board.hash);
+            assert_eq!(built.pawn_hash, board.pawn_hash);
+        }
+    }
+
+    #[test]
+    fn edit_position() {
+        let mut builder = BoardBuilder::new();
+        builder.put(Square::E1, Color::White, PieceType::King);
+        builder.put(Square::H1, Color::White, PieceType::Rook);
+        builder.put(Square::E8, Color::Black, PieceType::King);
+        assert_eq!(
+            builder.build().unwrap().fen(false),
+            "4k3/8/8/8/8/8/8/4K2R w - - 0 1"
+        );
+
+        builder.set_castling(Color::White, true, Some(File::H));
+        builder.set_side_to_move(Color::Black);
+        builder.set_halfmove_clock(3);
+        builder.set_fullmove_count(20);
+        assert_eq!(
+            builder.build().unwrap().fen(false),
+            "4k3/8/8/8/8/8/8/4K2R b K - 3 20"
+        );
+
+        // The rook gives check once the king is moved onto its file.
+        builder.remove(Square::E8);
+        builder.put(Square::H8, Color::Black, PieceType::King);
+        let board = builder.build().unwrap();
+        assert_eq!(board.checkers, Square::H1.bitboard());
+        assert_eq!(
+            board.to_builder().piece_on(Square::H8),
+            Some((Color::Black, PieceType::King))
+        );
+        builder.remove(Square::H8);
+        builder.put(Square::E8, Color::Black, PieceType::King);
+
+        builder.put(Square::D4, Color::White, PieceType::Pawn);
+        builder.put(Square::E4, Color::Black, PieceType::Pawn);
+        builder.set_en_passant(Some(File::D));
+        assert_eq!(
+            builder.build().unwrap().fen(false),
+            "4k3/8/8/8/3Pp3/8/8/4K2R b K d3 3 20"
+        );
+    }
+
+    #[test]
+    fn build_rejects_invalid() {
+        let base = Board::read_fen("4k3/8/8/8/3pP3/8/8/R3K3 b Q e3 0 1")
+            .unwrap()
+            .to_builder();
+        assert!(base.build().is_some());
+
+        let edits: [fn(&mut BoardBuilder); 8] = [
+            |b| {
+                b.remove(Square::E8);
+            },
+            |b| {
+                b.put(Square::A8, Color::Black, PieceType::King);
+            },
+            |b| {
+                b.put(Square::A8, Color::White, PieceType::Pawn);
+            },
+            // The side not to move is in check.
+            |b| {
+                b.put(Square::B4, Color::Black, PieceType::Bishop);
+            },
+            |b| {
+                b.remove(Square::A1);
+            },
+            |b| b.set_castling(Color::Black, true, Some(File::H)),
+            // The square the en passant pawn came from is occupied.
+            |b| {
+                b.put(Square::E2, Color::White, PieceType::Knight);
+            },
+            |b| b.set_halfmove_clock(100),
+        ];
+        for edit in edits {
+            let mut builder = base.clone();
+            edit(&mut builder);
+            assert!(builder.build().is_none(), "{builder:?}");
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::*;
+
+/// The squares the two knights occupy among the five squares left after placing the bishops
+/// and the queen, indexed by the knight part of the Scharnagl number.
+const KNIGHTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+/// The back rank arrangement with the given Scharnagl number, indexed by file.
+fn back_rank(index: u16) -> [PieceType; 8] {
+    let mut n = index as usize;
+    let mut rank = [None; 8];
+
+    rank[n % 4 * 2 + 1] = Some(PieceType::Bishop);
+    n /= 4;
+    rank[n % 4 * 2] = Some(PieceType::Bishop);
+    n /= 4;
+
+    let empty = |rank: &[Option<PieceType>; 8]| -> Vec<usize> {
+        (0..8).filter(|&f| rank[f].is_none()).collect()
+    };
+    rank[empty(&rank)[n % 6]] = Some(PieceType::Queen);
+    n /= 6;
+
+    let (a, b) = KNIGHTS[n];
+    let free = empty(&rank);
+    rank[free[a]] = Some(PieceType::Knight);
+    rank[free[b]] = Some(PieceType::Knight);
+
+    for (f, pt) in empty(&rank)
+        .into_iter()
+        .zip([PieceType::Rook, PieceType::King, PieceType::Rook])
+    {
+        rank[f] = Some(pt);
+    }
+
+    rank.map(Option::unwrap)
+}
+
+impl Board {
+    /// The Chess960 start position with the given Scharnagl number, where 518 is the classical
+    /// start position. Returns `None` unless `index < 960`.
+    pub fn chess960(index: u16) -> Option<Self> {
+        Self::double_chess960(index, index)
+    }
+
+    /// A Double Fischer Random start position, where white's and black's back ranks are the
+    /// Chess960 arrangements with the given Scharnagl numbers.
+    pub fn double_chess960(white_index: u16, black_index: u16) -> Option<Self> {
+        if white_index >= 960 || black_index >= 960 {
+            return None;
+        }
+
+        let mut board = Board::empty();
+        board.fullmove_count = 1;
+
+        for (color, index) in [(Color::White, white_index), (Color::Black, black_index)] {
+            let first_rank = Rank::R1.relative_to(color);
+            let pawn_rank = Rank::R2.relative_to(color);
+
+            for (f, pt) in back_rank(index).into_iter().enumerate() {
+                let f = File::from_idx(f as u8);
+                let sq = Square::from_file_rank(f, first_rank);
+                board.toggle_square(sq, color, pt);
+                board.mailbox[sq] = Some(pt);
+
+                let pawn = Square::from_file_rank(f, pawn_rank);
+                board.toggle_square(pawn, color, PieceType::Pawn);
+                board.mailbox[pawn] = Some(PieceType::Pawn);
+            }
+
+            let king = board.king(color);
+            for rook in board.colored_pieces(PieceType::Rook, color) {
+                board.set_castles(color, Some(rook.file()), rook.file() > king.file());
+            }
+        }
+
+        board.calc_pinned_and_checkers();
+
+        Some(board)
+    }
+
+    /// The Scharnagl number of this position, if it is a Chess960 start position.
+    pub fn chess960_index(&self) -> Option<u16> {
+        let files = |pt: PieceType| -> Vec<usize> {
+            (0..8)
+                .filter(|&f| {
+                    let sq = Square::from_file_rank(File::from_idx(f as u8), Rank::R1);
+                    self.colored_piece_on(sq, Color::White) == Some(pt)
+                })
+                .collect()
+        };
+
+        let [light, dark] = match files(PieceType::Bishop)[..] {
+            [a, b] if a % 2 == 1 && b % 2 == 0 => [a, b],
+            [a, b] if a % 2 == 0 && b % 2 == 1 => [b, a],
+            _ => return None,
+        };
+
+        let others = |excluded: &[usize]| -> Vec<usize> {
+            (0..8).filter(|f| !excluded.contains(f)).collect()
+        };
+        let &[queen] = &files(PieceType::Queen)[..] else {
+            return None;
+        };
+        let queen_idx = others(&[light, dark]).iter().position(|&f| f == queen)?;
+
+        let free = others(&[light, dark, queen]);
+        let &[a, b] = &files(PieceType::Knight)[..] else {
+            return None;
+        };
+        let knights = (
+            free.iter().position(|&f| f == a)?,
+            free.iter().position(|&f| f == b)?,
+        );
+        let knights_idx = KNIGHTS.iter().position(|&k| k == knights)?;
+
+        let index = (((knights_idx * 6 + queen_idx) * 4 + dark / 2) * 4 + light / 2) as u16;
+
+        // Everything else has to match as well, including the castling rights and the side to
+        // move, but not the move counters.
+        let start = Self::chess960(index)?;
+        (self.hash == start.hash
+            && self.occupied == start.occupied
+            && self.mailbox == start.mailbox)
+            .then_some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn scharnagl_numbers() {
+        assert_eq!(
+            Board::chess960(518).unwrap().fen(false),
+            Board::start_pos().fen(false)
+        );
+        assert_eq!(
+            Board::chess960(0).unwrap().fen(true),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1"
+        );
+        assert_eq!(
+            Board::chess960(959).unwrap().fen(true),
+            "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w CAca - 0 1"
+        );
+        assert!(Board::chess960(960).is_none());
+    }
+
+    #[test]
+    fn all_positions() {
+        let mut seen = HashSet::new();
+
+        for index in 0..960 {
+            let board = Board::chess960(index).unwrap();
+            assert_eq!(board.chess960_index(), Some(index));
+            assert!(seen.insert(board.hash));
+
+            let fen = board.fen(true);
+            let back_rank = fen.split('/').next().unwrap();
+            let bishops: Vec<_> = back_rank.match_indices('b').map(|(i, _)| i).collect();
+            assert_ne!(bishops[0] % 2, bishops[1] % 2, "{fen}");
+
+            let king = back_rank.find('k').unwrap();
+            assert!(back_rank.find('r').unwrap() < king && king < back_rank.rfind('r').unwrap());
+
+            assert_eq!(Board::read_fen(&fen).unwrap().hash, board.hash);
+            let mut moves = 0;
+            board.gen_moves(|m| moves += m.len());
+            assert!((18..=21).contains(&moves), "{fen}");
+        }
+    }
+
+    #[test]
+    fn double_chess960() {
+        let board = Board::double_chess960(518, 959).unwrap();
+        assert_eq!(
+            board.fen(true),
+            "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAca - 0 1"
+        );
+        assert_eq!(board.chess960_index(), None);
+        assert!(Board::double_chess960(0, 960).is_none());
+    }
+
+    #[test]
+    fn rejects_other_positions() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Qkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1",
+            "rnbqknbr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKNBR w KQkq - 0 1",
+        ] {
+            assert_eq!(
+                Board::read_fen(fen).unwrap().chess960_index(),
+                None,
+                "{fen}"
+            );
+        }
+
+        let mut board = Board::chess960(518).unwrap();
+        board.fullmove_count = 30;
+        assert_eq!(board.chess960_index(), Some(518));
+    }
+}
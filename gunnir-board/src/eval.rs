@@ -0,0 +1,522 @@
+use std::{
+    fmt,
+    ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign},
+};
+
+use crate::*;
+use enum_map::EnumMap;
+
+/// A pair of midgame and endgame scores. Evaluation terms are accumulated as `Score`s and only
+/// interpolated into a single value by the game phase at the very end.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub const ZERO: Self = Self::new(0, 0);
+
+    #[inline]
+    pub const fn new(mg: i32, eg: i32) -> Self {
+        Self { mg, eg }
+    }
+
+    #[inline]
+    pub const fn add(self, rhs: Self) -> Self {
+        Self::new(self.mg + rhs.mg, self.eg + rhs.eg)
+    }
+
+    #[inline]
+    pub const fn neg(self) -> Self {
+        Self::new(-self.mg, -self.eg)
+    }
+
+    /// Interpolates between the midgame and endgame score. `phase` ranges from `MAX_PHASE` in
+    /// the opening to 0 in a pawn endgame.
+    #[inline]
+    pub const fn taper(self, phase: i32) -> i32 {
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for Score {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Score::add(self, rhs)
+    }
+}
+
+impl Sub for Score {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Score::add(self, rhs.neg())
+    }
+}
+
+impl Neg for Score {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Score::neg(self)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: i32) -> Self {
+        Self::new(self.mg * rhs, self.eg * rhs)
+    }
+}
+
+impl AddAssign for Score {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Score {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl std::iter::Sum for Score {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, Add::add)
+    }
+}
+
+/// Material plus piece-square values for every piece on every square, from white's point of
+/// view. The board keeps a running sum of these in `Board::psqt`.
+static PSQT: [[[Score; Square::COUNT]; PieceType::COUNT]; Color::COUNT] = {
+    let mut psqt = [[[Score::ZERO; Square::COUNT]; PieceType::COUNT]; Color::COUNT];
+
+    let mut p = 0;
+    while p < PieceType::COUNT {
+        let mut s = 0;
+        while s < Square::COUNT {
+            // The tables are written rank 8 first, so for white we have to flip the rank.
+            psqt[0][p][s] = MATERIAL[p].add(PST[p][s ^ 56]);
+            psqt[1][p][s] = MATERIAL[p].add(PST[p][s]).neg();
+            s += 1;
+        }
+        p += 1;
+    }
+
+    psqt
+};
+
+#[inline]
+pub fn psqt(sq: Square, pt: PieceType, color: Color) -> Score {
+    PSQT[color.idx() as usize][pt.idx() as usize][sq.idx() as usize]
+}
+
+impl Board {
+    /// The game phase, ranging from `MAX_PHASE` in the opening to 0 in a pawn endgame. Phases
+    /// beyond `MAX_PHASE` (after promotions) are capped.
+    #[inline]
+    pub fn phase(&self) -> i32 {
+        let phase: i32 = PieceType::ALL
+            .iter()
+            .map(|&pt| self.pieces[pt].popcnt() as i32 * PHASE_WEIGHTS[pt.idx() as usize])
+            .sum();
+
+        phase.min(MAX_PHASE)
+    }
+}
+
+define_enum!(
+    /// The individual terms of the evaluation, as reported to a `Tracer`.
+    #[derive(Debug)]
+    pub enum Term {
+        Psqt,
+        Pawns,
+        PasserKingProximity,
+        Mobility,
+        KingAttack,
+        PawnShelter,
+        PawnStorm,
+        KingFiles,
+    }
+);
+
+/// Receives every evaluation term as it's computed, from the point of view of the color it
+/// belongs to. The evaluation is generic over this, so that `()` compiles down to nothing.
+pub trait Tracer {
+    /// Whether the tracer wants to see terms that are otherwise never computed per color.
+    const ENABLED: bool;
+
+    fn trace(&mut self, term: Term, color: Color, score: Score);
+
+    /// Looks up the weight at `idx` in `group`, multiplied by `count`, on behalf of `color`.
+    /// The evaluation goes through this for every weight it uses, so that the tuner can
+    /// record them.
+    #[inline(always)]
+    fn weight(&mut self, group: ParamGroup, idx: usize, _color: Color, count: i32) -> Score {
+        group.values()[idx] * count
+    }
+}
+
+impl Tracer for () {
+    const ENABLED: bool = false;
+
+    #[inline(always)]
+    fn trace(&mut self, _term: Term, _color: Color, _score: Score) {}
+}
+
+/// A breakdown of the evaluation of a position into its terms, see `evaluate_traced`.
+#[derive(Clone, Default, Debug)]
+pub struct EvalTrace {
+    pub terms: EnumMap<Term, EnumMap<Color, Score>>,
+    pub phase: i32,
+    /// The evaluation from white's point of view.
+    pub eval: i32,
+}
+
+impl Tracer for EvalTrace {
+    const ENABLED: bool = true;
+
+    #[inline]
+    fn trace(&mut self, term: Term, color: Color, score: Score) {
+        self.terms[term][color] += score;
+    }
+}
+
+impl EvalTrace {
+    /// The value of a term from white's point of view.
+    pub fn term(&self, term: Term) -> Score {
+        self.terms[term][Color::White] - self.terms[term][Color::Black]
+    }
+}
+
+impl fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pair = |s: Score| format!("{:>6} {:>6}", s.mg, s.eg);
+
+        writeln!(
+            f,
+            "{:<20} | {:^13} | {:^13} | {:^13}",
+            "Term", "White", "Black", "Total"
+        )?;
+        writeln!(f, "{:-<20}-+-{:-<13}-+-{:-<13}-+-{:-<13}", "", "", "", "")?;
+        for &term in Term::ALL {
+            writeln!(
+                f,
+                "{:<20} | {} | {} | {}",
+                format!("{term:?}"),
+                pair(self.terms[term][Color::White]),
+                pair(self.terms[term][Color::Black]),
+                pair(self.term(term)),
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Phase: {}/{MAX_PHASE}", self.phase)?;
+        write!(f, "Evaluation (white): {}", self.eval)
+    }
+}
+
+/// Statically evaluates the position from the point of view of the side to move, in centipawns.
+pub fn evaluate(board: &Board) -> i32 {
+    evaluate_with(board, &PawnEntry::compute(board), &mut ())
+}
+
+/// Like `evaluate`, but looks up the pawn structure evaluation in `pawns` first.
+pub fn evaluate_cached(board: &Board, pawns: &mut PawnTable) -> i32 {
+    evaluate_with(board, &pawns.probe(board), &mut ())
+}
+
+/// Like `evaluate`, but also returns how each term contributed to the result.
+pub fn evaluate_traced(board: &Board) -> (i32, EvalTrace) {
+    let mut trace = EvalTrace::default();
+    let pawns = PawnEntry::compute_traced(board, &mut trace);
+    let eval = evaluate_with(board, &pawns, &mut trace);
+
+    trace.phase = board.phase();
+    trace.eval = match board.stm {
+        Color::White => eval,
+        Color::Black => -eval,
+    };
+
+    (eval, trace)
+}
+
+pub(crate) fn evaluate_with<T: Tracer>(board: &Board, pawns: &PawnEntry, tracer: &mut T) -> i32 {
+    if T::ENABLED {
+        // The board only keeps the sum for both colors, so we recompute it per color.
+        for sq in board.occupied() {
+            let pt = board.piece_on(sq).unwrap();
+            let color = Color::from_idx(board.occupied[Color::Black].contains(sq) as u8);
+            // The tables are written rank 8 first, so for white we have to flip the rank.
+            let pst_sq = match color {
+                Color::White => sq.idx() ^ 56,
+                Color::Black => sq.idx(),
+            };
+
+            let score = tracer.weight(ParamGroup::Material, pt.idx() as usize, color, 1)
+                + tracer.weight(
+                    ParamGroup::Pst,
+                    pt.idx() as usize * Square::COUNT + pst_sq as usize,
+                    color,
+                    1,
+                );
+            tracer.trace(Term::Psqt, color, score);
+        }
+    }
+
+    let mut score = board.psqt + pawns.score + pawns.passer_king_proximity(board, tracer);
+
+    for &color in Color::ALL {
+        let ours = pieces(board, color, tracer) + king_shelter(board, color, tracer);
+        score += match color {
+            Color::White => ours,
+            Color::Black => -ours,
+        };
+    }
+
+    let white = score.taper(board.phase());
+
+    match board.stm {
+        Color::White => white,
+        Color::Black => -white,
+    }
+}
+
+/// The squares around the king, plus the ones in front of those.
+#[inline]
+fn king_zone(king: Square, color: Color) -> Bitboard {
+    let zone = king_moves(king) | king;
+    match color {
+        Color::White => zone | zone.shift::<Up>(1),
+        Color::Black => zone | zone.shift::<Down>(1),
+    }
+}
+
+/// Mobility and king attacks of `color`'s knights, bishops, rooks and queens.
+fn pieces<T: Tracer>(board: &Board, color: Color, tracer: &mut T) -> Score {
+    let blockers = board.occupied();
+    // Squares attacked by an enemy pawn don't count towards mobility, since a piece can
+    // rarely go there.
+    let safe = !(board.occupied[color]
+        | all_pawn_attacks(board.colored_pieces(PieceType::Pawn, !color), !color));
+    let zone = king_zone(board.king(!color), !color);
+
+    let mut mobility = Score::ZERO;
+    let mut attackers = 0;
+    let mut attack_units = 0;
+
+    for &pt in &PieceType::ALL[..4] {
+        let group = match pt {
+            PieceType::Knight => ParamGroup::KnightMobility,
+            PieceType::Bishop => ParamGroup::BishopMobility,
+            PieceType::Rook => ParamGroup::RookMobility,
+            _ => ParamGroup::QueenMobility,
+        };
+
+        for sq in board.colored_pieces(pt, color) {
+            let attacks = match pt {
+                PieceType::Knight => knight_moves(sq),
+                PieceType::Bishop => bishop_moves(sq, blockers),
+                PieceType::Rook => rook_moves(sq, blockers),
+                _ => rook_moves(sq, blockers) | bishop_moves(sq, blockers),
+            };
+
+            mobility += tracer.weight(group, (attacks & safe).popcnt() as usize, color, 1);
+
+            let hits = attacks & zone;
+            if hits.is_non_empty() {
+                attackers += 1;
+                attack_units += KING_ATTACK_WEIGHT[pt.idx() as usize] * hits.popcnt() as usize;
+            }
+        }
+    }
+
+    // A single attacker is rarely dangerous on its own.
+    let king_attack = if attackers >= 2 {
+        let units = attack_units.min(KING_ATTACK.len() - 1);
+        tracer.weight(ParamGroup::KingAttack, units, color, 1)
+    } else {
+        Score::ZERO
+    };
+
+    tracer.trace(Term::Mobility, color, mobility);
+    tracer.trace(Term::KingAttack, color, king_attack);
+
+    mobility + king_attack
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+    const FENS: &[&str] = &[
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9",
+    ];
+
+    fn psqt_from_scratch(board: &Board) -> Score {
+        Square::ALL
+            .iter()
+            .filter_map(|&sq| {
+                let pt = board.piece_on(sq)?;
+                let color = Color::from_idx(board.occupied[Color::Black].contains(sq) as u8);
+                Some(psqt(sq, pt, color))
+            })
+            .sum()
+    }
+
+    fn pawn_hash_from_scratch(board: &Board) -> u64 {
+        Color::ALL
+            .iter()
+            .flat_map(|&c| {
+                board
+                    .colored_pieces(PieceType::Pawn, c)
+                    .into_iter()
+                    .map(move |sq| (sq, c))
+            })
+            .fold(0, |h, (sq, c)| h ^ ZOBRIST.piece(sq, PieceType::Pawn, c))
+    }
+
+    /// Mirrors the FEN vertically and swaps the colors of all pieces and the side to move.
+    fn flip_fen(fen: &str) -> String {
+        let swap_case = |s: &str| -> String {
+            s.chars()
+                .map(|c| {
+                    if c.is_ascii_uppercase() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        c.to_ascii_uppercase()
+                    }
+                })
+                .collect()
+        };
+
+        let parts: Vec<_> = fen.split(' ').collect();
+        let pieces = parts[0].split('/').rev().collect::<Vec<_>>().join("/");
+        let stm = if parts[1] == "w" { "b" } else { "w" };
+        let ep = parts[3]
+            .replace('3', "x")
+            .replace('6', "3")
+            .replace('x', "6");
+
+        format!(
+            "{} {stm} {} {ep} {} {}",
+            swap_case(&pieces),
+            swap_case(parts[2]),
+            parts[4],
+            parts[5]
+        )
+    }
+
+    #[test]
+    fn incremental_updates() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+
+        for fen in FENS {
+            for _ in 0..20 {
+                let mut board = Board::read_fen(fen).unwrap();
+                assert_eq!(board.psqt, psqt_from_scratch(&board));
+
+                for _ in 0..100 {
+                    let mut moves = vec![];
+                    board.gen_moves(|m| moves.extend(m));
+                    if moves.is_empty() || board.halfmove_clock >= 99 {
+                        break;
+                    }
+
+                    board.make_move(moves[rng.random_range(0..moves.len())]);
+                    assert_eq!(board.psqt, psqt_from_scratch(&board));
+                    assert_eq!(board.pawn_hash, pawn_hash_from_scratch(&board));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn symmetric() {
+        let start = Board::start_pos();
+        assert_eq!(start.psqt, Score::ZERO);
+        assert_eq!(evaluate(&start), 0);
+
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+
+        for fen in FENS {
+            let mut board = Board::read_fen(fen).unwrap();
+
+            for _ in 0..50 {
+                let flipped = Board::read_fen(&flip_fen(&board.fen(true))).unwrap();
+                let transformed = board.flip_colors();
+                assert_eq!(transformed.fen(true), flipped.fen(true));
+                assert_eq!(transformed.hash, flipped.hash);
+                assert_eq!(board.psqt, -flipped.psqt);
+                assert_eq!(evaluate(&board), evaluate(&flipped));
+
+                let mut moves = vec![];
+                board.gen_moves(|m| moves.extend(m));
+                if moves.is_empty() || board.halfmove_clock >= 99 {
+                    break;
+                }
+                board.make_move(moves[rng.random_range(0..moves.len())]);
+            }
+        }
+    }
+
+    #[test]
+    fn trace_adds_up() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+
+        for fen in FENS {
+            let mut board = Board::read_fen(fen).unwrap();
+
+            for _ in 0..50 {
+                let (eval, trace) = evaluate_traced(&board);
+                assert_eq!(eval, evaluate(&board));
+
+                let sum: Score = Term::ALL.iter().map(|&t| trace.term(t)).sum();
+                assert_eq!(trace.term(Term::Psqt), board.psqt);
+                assert_eq!(sum.taper(trace.phase), trace.eval);
+
+                let mut moves = vec![];
+                board.gen_moves(|m| moves.extend(m));
+                if moves.is_empty() || board.halfmove_clock >= 99 {
+                    break;
+                }
+                board.make_move(moves[rng.random_range(0..moves.len())]);
+            }
+        }
+    }
+
+    #[test]
+    fn mobility_and_king_attack() {
+        let trace = |fen| evaluate_traced(&Board::read_fen(fen).unwrap()).1;
+
+        // The rook on a1 is boxed in by its own pieces.
+        let boxed = trace("4k3/8/8/8/8/8/PP6/RN2K3 w - - 0 1");
+        let open = trace("4k3/8/8/8/8/8/PP6/1N2K2R w - - 0 1");
+        assert!(
+            open.terms[Term::Mobility][Color::White].mg
+                > boxed.terms[Term::Mobility][Color::White].mg
+        );
+
+        // Queen and knight both bear down on the black king, while the white king is safe.
+        let attack = trace("6k1/5ppp/8/6NQ/8/8/5PPP/6K1 w - - 0 1");
+        let no_attack = trace("6k1/5ppp/8/8/8/8/Q4PPP/R5K1 w - - 0 1");
+        assert_eq!(no_attack.terms[Term::KingAttack][Color::White], Score::ZERO);
+        assert_eq!(attack.terms[Term::KingAttack][Color::Black], Score::ZERO);
+        assert!(attack.terms[Term::KingAttack][Color::White].mg > 0);
+    }
+}
@@ -0,0 +1,261 @@
+//! A compact encoding of games. Every move is stored as its index in the list of legal moves
+//! `gen_moves` generates, using just enough bits to tell all of them apart. Forced moves take
+//! no space at all, and a typical move takes 5 or 6 bits.
+//!
+//! Layout: the packed start position (see `Board::pack`), the number of moves as a LEB128
+//! varint, then the move indices, most significant bit first, padded with zero bits to a full
+//! byte.
+
+use crate::*;
+
+/// The number of bits needed to store an index into a list of `len` moves.
+#[inline]
+fn index_bits(len: usize) -> u32 {
+    usize::BITS - len.saturating_sub(1).leading_zeros()
+}
+
+fn legal_moves(board: &Board) -> Vec<Move> {
+    let mut moves = vec![];
+    board.gen_moves(|m| moves.extend(m));
+    moves
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: usize, bits: u32) {
+        for i in (0..bits).rev() {
+            if self.bits.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            let bit = (value >> i & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << (7 - self.bits % 8);
+            self.bits += 1;
+        }
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    bits: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, bits: u32) -> Option<usize> {
+        let mut value = 0;
+        for _ in 0..bits {
+            let byte = self.bytes.get(self.bits / 8)?;
+            value = value << 1 | (byte >> (7 - self.bits % 8) & 1) as usize;
+            self.bits += 1;
+        }
+        Some(value)
+    }
+
+    /// Whether everything was read, up to padding with zero bits.
+    fn finished(&mut self) -> bool {
+        let len = self.bytes.len() * 8;
+        len - self.bits < 8 && self.read((len - self.bits) as u32) == Some(0)
+    }
+}
+
+/// Encodes a game starting at `start`. Returns `None` if `start` has too many pieces to be
+/// packed, or if one of the moves is illegal or played after the game was drawn by the
+/// fifty-move rule.
+pub fn encode_game(start: &Board, moves: &[Move]) -> Option<Vec<u8>> {
+    // `BoardBuilder` doesn't build such boards, but the fields are public.
+    if start.occupied().popcnt() > 32 {
+        return None;
+    }
+    let mut bytes = start.pack().to_vec();
+
+    let mut count = moves.len();
+    loop {
+        let byte = (count & 0x7f) as u8;
+        count >>= 7;
+        if count == 0 {
+            bytes.push(byte);
+            break;
+        }
+        bytes.push(byte | 0x80);
+    }
+
+    let mut writer = BitWriter::default();
+    let mut board = *start;
+    for &mov in moves {
+        if board.halfmove_clock >= 100 {
+            return None;
+        }
+        let legal = legal_moves(&board);
+        let idx = legal.iter().position(|&m| m == mov)?;
+        writer.write(idx, index_bits(legal.len()));
+        board.make_move(mov);
+    }
+
+    bytes.extend(writer.bytes);
+    Some(bytes)
+}
+
+/// Decodes a game encoded by `encode_game` into its start position and moves. Returns `None`
+/// if the bytes aren't exactly a valid encoding.
+pub fn decode_game(bytes: &[u8]) -> Option<(Board, Vec<Move>)> {
+    let start = Board::unpack(bytes.get(..32)?.try_into().unwrap())?;
+
+    let mut count = 0usize;
+    let mut pos = 32;
+    for shift in (0..).step_by(7) {
+        let byte = *bytes.get(pos)?;
+        pos += 1;
+        // Longer varints would overflow, and no game gets anywhere near that long.
+        if shift > 28 {
+            return None;
+        }
+        count |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+
+    let mut reader = BitReader {
+        bytes: &bytes[pos..],
+        bits: 0,
+    };
+    let mut board = start;
+    let mut moves = vec![];
+    for _ in 0..count {
+        // Forced moves take no space, so a corrupt count isn't bounded by the input's length,
+        // but the fifty-move rule ensures it can't take us far.
+        if board.halfmove_clock >= 100 {
+            return None;
+        }
+        let legal = legal_moves(&board);
+        let mov = *legal.get(reader.read(index_bits(legal.len()))?)?;
+        board.make_move(mov);
+        moves.push(mov);
+    }
+
+    reader.finished().then_some((start, moves))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+    fn random_game(start: Board, rng: &mut SmallRng, len: usize) -> Vec<Move> {
+        let mut board = start;
+        let mut moves = vec![];
+        for _ in 0..len {
+            let legal = legal_moves(&board);
+            if legal.is_empty() || board.halfmove_clock >= 99 {
+                break;
+            }
+            let mov = legal[rng.random_range(0..legal.len())];
+            board.make_move(mov);
+            moves.push(mov);
+        }
+        moves
+    }
+
+    #[test]
+    fn index_widths() {
+        assert_eq!(index_bits(1), 0);
+        assert_eq!(index_bits(2), 1);
+        assert_eq!(index_bits(20), 5);
+        assert_eq!(index_bits(32), 5);
+        assert_eq!(index_bits(33), 6);
+        assert_eq!(index_bits(218), 8);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+
+        for line in include_str!("../perft960.txt").lines().step_by(8) {
+            let start = Board::read_fen(line.split('\t').nth(1).unwrap().trim()).unwrap();
+
+            for len in [0, 1, 200] {
+                let moves = random_game(start, &mut rng, len);
+                let bytes = encode_game(&start, &moves).unwrap();
+                assert!(bytes.len() <= 32 + 2 + moves.len());
+
+                let (board, decoded) = decode_game(&bytes).unwrap();
+                assert_eq!(board.fen(true), start.fen(true));
+                assert_eq!(decoded, moves);
+            }
+        }
+    }
+
+    #[test]
+    fn forced_moves_are_free() {
+        // Black's only legal move is to take the queen.
+        let start = Board::read_fen("7k/6Q1/8/8/8/8/8/K7 b - - 0 1").unwrap();
+        let moves = [start.parse_move("h8g7", false).unwrap()];
+        let bytes = encode_game(&start, &moves).unwrap();
+        assert_eq!(bytes.len(), 33);
+        assert_eq!(decode_game(&bytes).unwrap().1, moves);
+    }
+
+    #[test]
+    fn rejects_corrupt_streams() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+        let start = Board::start_pos();
+        let moves = random_game(start, &mut rng, 100);
+        let bytes = encode_game(&start, &moves).unwrap();
+
+        for len in 0..bytes.len() {
+            assert!(decode_game(&bytes[..len]).is_none());
+        }
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(decode_game(&trailing).is_none());
+
+        // Flipping bits must never panic, though it may well decode to some other game.
+        for i in 0..bytes.len() * 8 {
+            let mut corrupt = bytes.clone();
+            corrupt[i / 8] ^= 1 << (i % 8);
+            if let Some((board, moves)) = decode_game(&corrupt) {
+                assert_eq!(encode_game(&board, &moves).unwrap(), corrupt);
+            }
+        }
+
+        // A start position where the side not to move is in check, followed by two moves. One
+        // of the indices is white taking the king, which must be rejected rather than played.
+        let mut in_check = Board::read_fen("4k3/8/8/8/8/8/8/4R1K1 b - - 0 1")
+            .unwrap()
+            .pack()
+            .to_vec();
+        in_check[24] = 0;
+        in_check.push(2);
+        for byte in 0..=u8::MAX {
+            let mut corrupt = in_check.clone();
+            corrupt.push(byte);
+            assert!(decode_game(&corrupt).is_none());
+        }
+
+        // A start position with more pieces than `Board::pack` has room for can't be encoded.
+        let mut crowded = start;
+        for sq in Rank::R3.bitboard() | Rank::R4.bitboard() | Rank::R5.bitboard() {
+            crowded.toggle_square(sq, Color::White, PieceType::Knight);
+            crowded.mailbox[sq] = Some(PieceType::Knight);
+        }
+        assert!(encode_game(&crowded, &[]).is_none());
+
+        // An illegal move can't be encoded.
+        let e2e5 = Move::new(Square::E2, Square::E5, MoveFlag::None);
+        assert!(encode_game(&start, &[e2e5]).is_none());
+
+        // Neither can a move after the fifty-move rule.
+        let drawn = Board::read_fen("4k3/8/8/8/8/8/8/4K3 w - - 99 80").unwrap();
+        let mut moves = vec![drawn.parse_move("e1e2", false).unwrap()];
+        assert!(encode_game(&drawn, &moves).is_some());
+        let mut board = drawn;
+        board.make_move(moves[0]);
+        moves.push(legal_moves(&board)[0]);
+        assert!(encode_game(&drawn, &moves).is_none());
+    }
+}
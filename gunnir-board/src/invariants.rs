@@ -0,0 +1,324 @@
+use crate::*;
+
+/// Something about a board that doesn't add up, as found by `Board::check_invariants`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum InvariantViolation {
+    /// The mailbox disagrees with the piece bitboards about what's on a square.
+    Mailbox {
+        sq: Square,
+        mailbox: Option<PieceType>,
+        bitboards: Option<PieceType>,
+    },
+    /// Squares in more than one piece type's bitboard.
+    OverlappingPieces(Bitboard),
+    /// Squares occupied by both colors.
+    OverlappingColors(Bitboard),
+    /// Squares with a piece but no color, or the other way around.
+    Occupancy(Bitboard),
+    KingCount {
+        color: Color,
+        count: u8,
+    },
+    Hash {
+        expected: u64,
+        actual: u64,
+    },
+    PawnHash {
+        expected: u64,
+        actual: u64,
+    },
+    Psqt {
+        expected: Score,
+        actual: Score,
+    },
+    Pinned {
+        expected: Bitboard,
+        actual: Bitboard,
+    },
+    Checkers {
+        expected: Bitboard,
+        actual: Bitboard,
+    },
+    /// The side that just moved left its king in check.
+    OpponentInCheck,
+    /// A castling right without its rook on the first rank on the right side of the king.
+    CastlingRook {
+        color: Color,
+        file: File,
+        short: bool,
+    },
+    /// An en passant file without a pawn that could just have moved two squares there.
+    EnPassantPawn(File),
+    /// With the `strict-en-passant` feature, an en passant file that can't be taken on.
+    EnPassantIllegal(File),
+}
+
+impl Board {
+    /// Recomputes everything derived from the pieces, the side to move, and the castling and
+    /// en passant state from scratch, and compares it to what the board has stored. Meant for
+    /// debugging `make_move`, which calls this after every move with the `debug-invariants`
+    /// feature.
+    pub fn check_invariants(&self) -> Result<(), Vec<InvariantViolation>> {
+        use InvariantViolation::*;
+        let mut violations = vec![];
+
+        let mut seen = Bitboard::EMPTY;
+        for pt in PieceType::ALL.iter().copied() {
+            let overlap = seen & self.pieces[pt];
+            if overlap.is_non_empty() {
+                violations.push(OverlappingPieces(overlap));
+            }
+            seen |= self.pieces[pt];
+        }
+
+        for sq in Square::ALL.iter().copied() {
+            let bitboards = PieceType::ALL
+                .iter()
+                .copied()
+                .find(|&pt| self.pieces[pt].contains(sq));
+            if self.mailbox[sq] != bitboards {
+                violations.push(Mailbox {
+                    sq,
+                    mailbox: self.mailbox[sq],
+                    bitboards,
+                });
+            }
+        }
+
+        let overlap = self.occupied[Color::White] & self.occupied[Color::Black];
+        if overlap.is_non_empty() {
+            violations.push(OverlappingColors(overlap));
+        }
+
+        let mismatch = seen ^ self.occupied();
+        if mismatch.is_non_empty() {
+            violations.push(Occupancy(mismatch));
+        }
+
+        for color in Color::ALL.iter().copied() {
+            let count = self.colored_pieces(PieceType::King, color).popcnt();
+            if count != 1 {
+                violations.push(KingCount { color, count });
+            }
+        }
+
+        // Everything below needs a sane board to work with.
+        if !violations.is_empty() {
+            return Err(violations);
+        }
+
+        let mut hash = 0;
+        let mut pawn_hash = 0;
+        let mut psqt_sum = Score::ZERO;
+        for color in Color::ALL.iter().copied() {
+            for sq in self.occupied[color] {
+                let pt = self.mailbox[sq].unwrap();
+                hash ^= ZOBRIST.piece(sq, pt, color);
+                if pt == PieceType::Pawn {
+                    pawn_hash ^= ZOBRIST.piece(sq, pt, color);
+                }
+                psqt_sum += psqt(sq, pt, color);
+            }
+
+            let rights = self.castles[color];
+            for file in [rights.short, rights.long].into_iter().flatten() {
+                hash ^= ZOBRIST.castles(file, color);
+            }
+        }
+        if self.stm == Color::Black {
+            hash ^= ZOBRIST.black_to_move;
+        }
+        if let Some(file) = self.en_passant {
+            hash ^= ZOBRIST.en_passant(file);
+        }
+
+        if hash != self.hash {
+            violations.push(Hash {
+                expected: hash,
+                actual: self.hash,
+            });
+        }
+        if pawn_hash != self.pawn_hash {
+            violations.push(PawnHash {
+                expected: pawn_hash,
+                actual: self.pawn_hash,
+            });
+        }
+        if psqt_sum != self.psqt {
+            violations.push(Psqt {
+                expected: psqt_sum,
+                actual: self.psqt,
+            });
+        }
+
+        let mut fresh = *self;
+        fresh.calc_pinned_and_checkers();
+        if fresh.pinned != self.pinned {
+            violations.push(Pinned {
+                expected: fresh.pinned,
+                actual: self.pinned,
+            });
+        }
+        if fresh.checkers != self.checkers {
+            violations.push(Checkers {
+                expected: fresh.checkers,
+                actual: self.checkers,
+            });
+        }
+
+        let mut passed = *self;
+        passed.stm = !self.stm;
+        passed.calc_pinned_and_checkers();
+        if passed.checkers.is_non_empty() {
+            violations.push(OpponentInCheck);
+        }
+
+        for color in Color::ALL.iter().copied() {
+            let king = self.king(color);
+            let rights = self.castles[color];
+            for (file, short) in [(rights.short, true), (rights.long, false)] {
+                let Some(file) = file else {
+                    continue;
+                };
+
+                let rook = Square::from_file_rank(file, Rank::R1.relative_to(color));
+                if king.rank() != rook.rank()
+                    || (file > king.file()) != short
+                    || self.colored_piece_on(rook, color) != Some(PieceType::Rook)
+                {
+                    violations.push(CastlingRook { color, file, short });
+                }
+            }
+        }
+
+        if let Some(file) = self.en_passant {
+            let them = !self.stm;
+            let pawn = Square::from_file_rank(file, Rank::R4.relative_to(them));
+            let skipped = Square::from_file_rank(file, Rank::R3.relative_to(them));
+            let origin = Square::from_file_rank(file, Rank::R2.relative_to(them));
+
+            if self.colored_piece_on(pawn, them) != Some(PieceType::Pawn)
+                || self.piece_on(skipped).is_some()
+                || self.piece_on(origin).is_some()
+            {
+                violations.push(EnPassantPawn(file));
+            } else if cfg!(feature = "strict-en-passant") && !self.has_legal_en_passant() {
+                violations.push(EnPassantIllegal(file));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use InvariantViolation::*;
+    use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    #[test]
+    fn random_playouts() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+
+        for line in include_str!("../perft960.txt").lines().step_by(4) {
+            let mut board = Board::read_fen(line.split('\t').nth(1).unwrap().trim()).unwrap();
+            for _ in 0..100 {
+                assert_eq!(board.check_invariants(), Ok(()), "{}", board.fen(true));
+
+                let mut moves = vec![];
+                board.gen_moves(|m| moves.extend(m));
+                if moves.is_empty() || board.halfmove_clock >= 99 {
+                    break;
+                }
+                board.make_move(moves[rng.random_range(0..moves.len())]);
+            }
+        }
+    }
+
+    #[test]
+    fn detects_violations() {
+        let board = Board::read_fen(KIWIPETE).unwrap();
+        let violations = |change: fn(&mut Board)| {
+            let mut board = board;
+            change(&mut board);
+            board.check_invariants().unwrap_err()
+        };
+
+        assert_eq!(
+            violations(|b| b.mailbox[Square::A2] = Some(PieceType::Knight)),
+            [Mailbox {
+                sq: Square::A2,
+                mailbox: Some(PieceType::Knight),
+                bitboards: Some(PieceType::Pawn),
+            }]
+        );
+        assert_eq!(
+            violations(|b| b.occupied[Color::Black] |= Square::A2),
+            [OverlappingColors(Square::A2.bitboard())]
+        );
+        assert_eq!(
+            violations(|b| b.pieces[PieceType::King] ^= Square::E8),
+            [
+                Mailbox {
+                    sq: Square::E8,
+                    mailbox: Some(PieceType::King),
+                    bitboards: None,
+                },
+                Occupancy(Square::E8.bitboard()),
+                KingCount {
+                    color: Color::Black,
+                    count: 0,
+                },
+            ]
+        );
+        assert!(matches!(violations(|b| b.hash ^= 1)[..], [Hash { .. }]));
+        assert!(matches!(
+            violations(|b| b.pawn_hash = 0)[..],
+            [PawnHash { .. }]
+        ));
+        assert!(matches!(
+            violations(|b| b.psqt = Score::ZERO)[..],
+            [Psqt { .. }]
+        ));
+        assert!(matches!(
+            violations(|b| b.pinned |= Square::A2)[..],
+            [Pinned { .. }]
+        ));
+        assert!(matches!(
+            violations(|b| b.checkers |= Square::E8)[..],
+            [Checkers { .. }]
+        ));
+
+        // Hashes are compared too, so change state only through the setters.
+        assert_eq!(
+            violations(|b| b.set_castles(Color::White, Some(File::B), false)),
+            [CastlingRook {
+                color: Color::White,
+                file: File::B,
+                short: false,
+            }]
+        );
+        assert_eq!(
+            violations(|b| b.set_en_passant(Some(File::C))),
+            [EnPassantPawn(File::C)]
+        );
+
+        // `read_fen` rejects such positions, so pass the move by hand.
+        assert!(Board::read_fen("4k3/8/8/8/8/8/4R3/4K3 w - - 0 1").is_none());
+        let mut opponent_in_check = Board::read_fen("4k3/8/8/8/8/8/4R3/4K3 b - - 0 1").unwrap();
+        opponent_in_check.stm = Color::White;
+        opponent_in_check.hash ^= ZOBRIST.black_to_move;
+        opponent_in_check.calc_pinned_and_checkers();
+        assert_eq!(
+            opponent_in_check.check_invariants(),
+            Err(vec![OpponentInCheck])
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use crate::*;
+
+/// Scores the pawns on the king's file and the two files next to it: our own pawns shelter
+/// the king, enemy pawns storming towards it are dangerous, and files without our pawns give
+/// the enemy rooks a way in.
+pub fn king_shelter<T: Tracer>(board: &Board, color: Color, tracer: &mut T) -> Score {
+    let king = board.king(color);
+    let ours = board.colored_pieces(PieceType::Pawn, color);
+    let theirs = board.colored_pieces(PieceType::Pawn, !color);
+
+    // Pawns behind the king can't shelter it, and enemy pawns there have already passed it.
+    let ahead = king.rank().bitboard() | front_span(king.rank().bitboard(), color);
+
+    // Rank 1 can never hold one of our pawns, and the back rank from our point of view can
+    // never hold one of theirs, so index 0 doubles as "no pawn on this file".
+    let nearest = |pawns: Bitboard| {
+        pawns
+            .into_iter()
+            .map(|sq| sq.rank().relative_to(color).idx() as usize)
+            .min()
+            .unwrap_or(0)
+    };
+
+    let mut shelter = Score::ZERO;
+    let mut storm = Score::ZERO;
+    let mut files = Score::ZERO;
+
+    // A king on the edge is still sheltered by the three files closest to it.
+    let center = king.file().idx().clamp(1, 6);
+    for file in File::ALL[center as usize - 1..=center as usize + 1].iter() {
+        let file = file.bitboard();
+
+        shelter += tracer.weight(ParamGroup::Shelter, nearest(ours & file & ahead), color, 1);
+        storm += tracer.weight(ParamGroup::Storm, nearest(theirs & file & ahead), color, 1);
+
+        if (ours & file).is_empty() {
+            let group = if (theirs & file).is_empty() {
+                ParamGroup::KingOpenFile
+            } else {
+                ParamGroup::KingSemiOpenFile
+            };
+            files += tracer.weight(group, 0, color, 1);
+        }
+    }
+
+    tracer.trace(Term::PawnShelter, color, shelter);
+    tracer.trace(Term::PawnStorm, color, storm);
+    tracer.trace(Term::KingFiles, color, files);
+
+    shelter + storm + files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shelter(fen: &str) -> Score {
+        let board = Board::read_fen(fen).unwrap();
+        king_shelter(&board, Color::White, &mut ())
+    }
+
+    #[test]
+    fn shelter_and_storm() {
+        let intact = shelter("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1");
+        assert_eq!(intact, SHELTER[1] * 3);
+
+        // Pushing a shelter pawn weakens the king, losing it entirely is worse.
+        let pushed = shelter("6k1/5ppp/8/8/8/6P1/5P1P/6K1 w - - 0 1");
+        let missing = shelter("6k1/5ppp/8/8/8/8/5P1P/6K1 w - - 0 1");
+        assert!(intact.mg > pushed.mg && pushed.mg > missing.mg);
+        assert_eq!(
+            missing,
+            SHELTER[1] * 2 + SHELTER[0] + STORM[6] + KING_SEMI_OPEN_FILE
+        );
+
+        // An enemy pawn storming the g-file.
+        let stormed = shelter("6k1/5p1p/8/8/8/6p1/5PPP/6K1 w - - 0 1");
+        assert_eq!(stormed, SHELTER[1] * 3 + STORM[2]);
+
+        // The king on the edge looks at the three closest files, g and h-file being open.
+        let open = shelter("k7/8/8/8/8/8/5P2/7K w - - 0 1");
+        assert_eq!(open, SHELTER[1] + SHELTER[0] * 2 + KING_OPEN_FILE * 2);
+    }
+}
@@ -1,13 +1,40 @@
 pub use gunnir_common::*;
 
 pub mod board;
+pub mod builder;
+pub mod chess960;
+pub mod eval;
+pub mod game_codec;
+pub mod invariants;
+pub mod king_safety;
 pub mod movegen;
+pub mod nnue;
+pub mod observer;
+pub mod pack;
+pub mod params;
+pub mod pawns;
+pub mod perft;
+pub mod perft_suite;
 pub mod slider_moves;
+pub mod transform;
+pub mod tuning;
 pub mod zobrist;
 
 pub use board::*;
+pub use builder::*;
+pub use eval::*;
+pub use game_codec::*;
+pub use invariants::*;
+pub use king_safety::*;
+pub use nnue::*;
+pub use observer::*;
+pub use params::*;
+pub use pawns::*;
+pub use perft::*;
+pub use perft_suite::*;
 pub use slider_moves::*;
+pub use tuning::*;
 pub use zobrist::*;
 
 #[cfg(test)]
-mod perft;
\ No newline at end of file
+mod reference_movegen;
@@ -69,6 +69,14 @@ impl Board {
             }
         }
 
+        self.add_en_passant_moves(visitor);
+    }
+
+    #[inline]
+    fn add_en_passant_moves<V: FnMut(PieceMoves)>(&self, visitor: &mut V) {
+        let blockers = self.occupied();
+        let pawns = self.colored_pieces(PieceType::Pawn, self.stm);
+
         if let Some(ep) = self.en_passant {
             let (dst_rank, push_dir) = (Rank::R6.relative_to(self.stm), self.stm.signum());
 
@@ -110,6 +118,17 @@ impl Board {
         }
     }
 
+    /// Whether the side to move can legally take en passant. Without the `strict-en-passant`
+    /// feature, `en_passant` may be set even if it can't, since that only depends on whether
+    /// an enemy pawn attacks the skipped square.
+    pub fn has_legal_en_passant(&self) -> bool {
+        let mut found = false;
+        if self.checkers.popcnt() < 2 {
+            self.add_en_passant_moves(&mut |_| found = true);
+        }
+        found
+    }
+
     #[inline]
     fn add_knight_moves<const IN_CHECK: bool, V: FnMut(PieceMoves)>(&self, visitor: &mut V) {
         let targets = self.targets::<IN_CHECK>();
@@ -0,0 +1,480 @@
+//! An efficiently updatable neural network evaluation. The network is a (768 -> HIDDEN)x2 -> 1
+//! perspective network: every piece on the board activates one of 768 input features, both
+//! from white's and from black's point of view. The resulting hidden layers (the accumulators)
+//! only change by a few rows per move, so we update them incrementally. The hidden layer of the
+//! side to move and the one of its opponent are then concatenated, activated with a squared
+//! clipped ReLU and reduced to a single output.
+
+use std::sync::LazyLock;
+
+use crate::*;
+use enum_map::EnumMap;
+
+pub const INPUTS: usize = 768;
+pub const HIDDEN: usize = 128;
+
+/// Quantization of the feature transformer.
+pub const QA: i32 = 255;
+/// Quantization of the output layer.
+pub const QB: i32 = 64;
+/// Converts the network output to centipawns.
+pub const SCALE: i32 = 400;
+
+/// The network that ships with the engine. It's bootstrapped from the piece-square tables by
+/// `examples/bootstrap_nnue.rs` until we have trained a real one.
+pub static DEFAULT_NETWORK: LazyLock<Box<Network>> = LazyLock::new(|| {
+    Network::from_bytes(include_bytes!("../nets/default.nnue")).expect("Invalid default network")
+});
+
+/// The quantized network weights. In a network file, all values are stored as little endian
+/// `i16`s in field order, optionally followed by padding. Output weights must lie within
+/// `-128..=128`, so that the SIMD paths can multiply them by an activation without overflow.
+/// Likewise, a feature bias plus 32 feature weights, one for every piece on a full board, must
+/// fit into an `i16`.
+#[repr(C, align(64))]
+pub struct Network {
+    pub feature_weights: [[i16; HIDDEN]; INPUTS],
+    pub feature_bias: [i16; HIDDEN],
+    /// The weights for the side to move's accumulator, followed by the ones for the opponent's.
+    pub output_weights: [[i16; HIDDEN]; 2],
+    pub output_bias: i16,
+}
+
+impl Network {
+    /// The size of a network file without padding.
+    pub const BYTES: usize = (INPUTS * HIDDEN + HIDDEN + 2 * HIDDEN + 1) * 2;
+
+    /// A network with all weights set to 0.
+    pub fn zeroed() -> Box<Self> {
+        // SAFETY: The network consists of nothing but integers, for which all zeroes is a valid
+        // bit pattern. We allocate directly on the heap, since the network is too large for
+        // the stack of some threads.
+        unsafe { Box::<Self>::new_zeroed().assume_init() }
+    }
+
+    /// Reads a network in the format described on `Network`. Returns `None` if `bytes` is too
+    /// short, or if a weight is out of range.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Box<Self>> {
+        if bytes.len() < Self::BYTES {
+            return None;
+        }
+
+        let mut values = bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]));
+
+        let mut net = Self::zeroed();
+        net.feature_weights
+            .iter_mut()
+            .flatten()
+            .chain(&mut net.feature_bias)
+            .chain(net.output_weights.iter_mut().flatten())
+            .chain([&mut net.output_bias])
+            .for_each(|w| *w = values.next().unwrap());
+
+        if net
+            .output_weights
+            .iter()
+            .flatten()
+            .any(|w| !(-128..=128).contains(w))
+        {
+            return None;
+        }
+
+        let max_weight = net
+            .feature_weights
+            .iter()
+            .flatten()
+            .map(|&w| (w as i32).abs())
+            .max()
+            .unwrap();
+        if net
+            .feature_bias
+            .iter()
+            .any(|&b| (b as i32).abs() + 32 * max_weight > i16::MAX as i32)
+        {
+            return None;
+        }
+
+        Some(net)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.feature_weights
+            .iter()
+            .flatten()
+            .chain(&self.feature_bias)
+            .chain(self.output_weights.iter().flatten())
+            .chain([&self.output_bias])
+            .flat_map(|w| w.to_le_bytes())
+            .collect()
+    }
+
+    /// Evaluates the position the accumulator belongs to from the point of view of `stm`, in
+    /// centipawns.
+    pub fn evaluate(&self, acc: &Accumulator, stm: Color) -> i32 {
+        let (ours, theirs) = (&acc.values[stm], &acc.values[!stm]);
+
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: We just checked that the CPU supports AVX2.
+            let sum = unsafe {
+                avx2::screlu_dot(ours, &self.output_weights[0])
+                    + avx2::screlu_dot(theirs, &self.output_weights[1])
+            };
+            return self.finish(sum);
+        }
+
+        self.finish(
+            scalar::screlu_dot(ours, &self.output_weights[0])
+                + scalar::screlu_dot(theirs, &self.output_weights[1]),
+        )
+    }
+
+    /// Like `evaluate`, but always uses the scalar fallback.
+    pub fn evaluate_scalar(&self, acc: &Accumulator, stm: Color) -> i32 {
+        self.finish(
+            scalar::screlu_dot(&acc.values[stm], &self.output_weights[0])
+                + scalar::screlu_dot(&acc.values[!stm], &self.output_weights[1]),
+        )
+    }
+
+    /// Dequantizes the output layer's sum. The squared activation is in units of `QA * QA`, so
+    /// we first get rid of one factor of `QA`. Scaling happens in 64 bits, since with the
+    /// largest weights the product no longer fits into an `i32`.
+    #[inline]
+    fn finish(&self, sum: i32) -> i32 {
+        let sum = (sum / QA + self.output_bias as i32) as i64;
+        (sum * SCALE as i64 / (QA * QB) as i64) as i32
+    }
+}
+
+/// The input feature for a piece, as seen from `perspective`. Features are ordered by whether
+/// the piece is ours or theirs, then pawn, knight, bishop, rook, queen, king, then square, where
+/// the board is flipped vertically for black.
+#[inline]
+pub fn feature(perspective: Color, sq: Square, pt: PieceType, color: Color) -> usize {
+    let piece = match pt {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    };
+    let sq = match perspective {
+        Color::White => sq.idx() as usize,
+        Color::Black => sq.idx() as usize ^ 56,
+    };
+
+    (color != perspective) as usize * 384 + piece * 64 + sq
+}
+
+/// The hidden layer of the network, from both colors' points of view.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[repr(C, align(64))]
+pub struct Accumulator {
+    pub values: EnumMap<Color, [i16; HIDDEN]>,
+}
+
+impl Accumulator {
+    /// Computes the accumulator for `board` from scratch.
+    pub fn new(net: &Network, board: &Board) -> Self {
+        let mut acc = Self {
+            values: EnumMap::from_fn(|_| net.feature_bias),
+        };
+
+        for sq in board.occupied() {
+            let pt = board.piece_on(sq).unwrap();
+            let color = Color::from_idx(board.occupied[Color::Black].contains(sq) as u8);
+            for &perspective in Color::ALL {
+                let row = &net.feature_weights[feature(perspective, sq, pt, color)];
+                add(&mut acc.values[perspective], row);
+            }
+        }
+
+        acc
+    }
+
+    /// Applies the piece updates of a move, as collected by `Board::make_move_with`.
+    pub fn update(&mut self, net: &Network, updates: &PieceUpdates) {
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: We just checked that the CPU supports AVX2.
+            unsafe { avx2::update(self, net, updates) };
+            return;
+        }
+
+        scalar::update(self, net, updates);
+    }
+}
+
+#[inline(always)]
+fn add(values: &mut [i16; HIDDEN], row: &[i16; HIDDEN]) {
+    for (v, w) in values.iter_mut().zip(row) {
+        *v += w;
+    }
+}
+
+#[inline(always)]
+fn sub(values: &mut [i16; HIDDEN], row: &[i16; HIDDEN]) {
+    for (v, w) in values.iter_mut().zip(row) {
+        *v -= w;
+    }
+}
+
+mod scalar {
+    use super::*;
+
+    /// Updates are simple enough for the compiler to vectorize by itself, we just have to make
+    /// sure they are inlined into functions compiled with the right target features.
+    #[inline(always)]
+    pub fn update(acc: &mut Accumulator, net: &Network, updates: &PieceUpdates) {
+        for update in updates.iter() {
+            for &perspective in Color::ALL {
+                let f = feature(perspective, update.sq, update.pt, update.color);
+                let row = &net.feature_weights[f];
+                if update.added {
+                    add(&mut acc.values[perspective], row);
+                } else {
+                    sub(&mut acc.values[perspective], row);
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub fn screlu_dot(values: &[i16; HIDDEN], weights: &[i16; HIDDEN]) -> i32 {
+        values
+            .iter()
+            .zip(weights)
+            .map(|(&v, &w)| {
+                let v = (v as i32).clamp(0, QA);
+                v * v * w as i32
+            })
+            .sum()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    use super::*;
+
+    const LANES: usize = 16;
+
+    #[target_feature(enable = "avx2")]
+    pub fn update(acc: &mut Accumulator, net: &Network, updates: &PieceUpdates) {
+        scalar::update(acc, net, updates)
+    }
+
+    /// Computes `v * w` as 16 bit integers first, which can't overflow since `v <= QA` and
+    /// `|w| <= 128`, and then multiplies that by `v` while summing adjacent pairs into 32 bits.
+    #[target_feature(enable = "avx2")]
+    pub fn screlu_dot(values: &[i16; HIDDEN], weights: &[i16; HIDDEN]) -> i32 {
+        let zero = _mm256_setzero_si256();
+        let qa = _mm256_set1_epi16(QA as i16);
+        let mut sum = _mm256_setzero_si256();
+
+        for (v, w) in values.chunks_exact(LANES).zip(weights.chunks_exact(LANES)) {
+            // SAFETY: Both chunks hold exactly 16 `i16`s, i.e. 256 bits.
+            let (v, w) = unsafe {
+                (
+                    _mm256_loadu_si256(v.as_ptr().cast()),
+                    _mm256_loadu_si256(w.as_ptr().cast()),
+                )
+            };
+            let v = _mm256_min_epi16(_mm256_max_epi16(v, zero), qa);
+            let vw = _mm256_mullo_epi16(v, w);
+            sum = _mm256_add_epi32(sum, _mm256_madd_epi16(vw, v));
+        }
+
+        let sum = _mm_add_epi32(
+            _mm256_castsi256_si128(sum),
+            _mm256_extracti128_si256::<1>(sum),
+        );
+        let sum = _mm_add_epi32(sum, _mm_unpackhi_epi64(sum, sum));
+        let sum = _mm_add_epi32(sum, _mm_shuffle_epi32::<0b01>(sum));
+        _mm_cvtsi128_si32(sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+    const FENS: &[&str] = &[
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9",
+    ];
+
+    /// A network with random weights, so that every feature has a distinct effect.
+    fn random_network(rng: &mut SmallRng) -> Box<Network> {
+        let mut net = Network::zeroed();
+        net.feature_weights
+            .iter_mut()
+            .flatten()
+            .chain(&mut net.feature_bias)
+            .for_each(|w| *w = rng.random_range(-64..64));
+        net.output_weights
+            .iter_mut()
+            .flatten()
+            .for_each(|w| *w = rng.random_range(-128..=128));
+        net.output_bias = rng.random_range(-1000..1000);
+        net
+    }
+
+    #[test]
+    fn incremental_updates() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+        let net = random_network(&mut rng);
+
+        for fen in FENS {
+            for _ in 0..10 {
+                let mut board = Board::read_fen(fen).unwrap();
+                let mut acc = Accumulator::new(&net, &board);
+                let mut scalar_acc = acc.clone();
+
+                for _ in 0..100 {
+                    let mut moves = vec![];
+                    board.gen_moves(|m| moves.extend(m));
+                    if moves.is_empty() || board.halfmove_clock >= 99 {
+                        break;
+                    }
+
+                    let mut updates = PieceUpdates::default();
+                    board.make_move_with(moves[rng.random_range(0..moves.len())], &mut updates);
+                    acc.update(&net, &updates);
+                    scalar::update(&mut scalar_acc, &net, &updates);
+
+                    let scratch = Accumulator::new(&net, &board);
+                    assert_eq!(acc, scratch);
+                    assert_eq!(scalar_acc, scratch);
+                    assert_eq!(
+                        net.evaluate(&acc, board.stm),
+                        net.evaluate_scalar(&acc, board.stm)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scalar_matches_simd() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+        let net = random_network(&mut rng);
+
+        for _ in 0..100 {
+            // Make sure we hit both ends of the clipping range.
+            let acc = Accumulator {
+                values: EnumMap::from_fn(|_| std::array::from_fn(|_| rng.random_range(-300..300))),
+            };
+            for &stm in Color::ALL {
+                assert_eq!(net.evaluate(&acc, stm), net.evaluate_scalar(&acc, stm));
+            }
+        }
+    }
+
+    #[test]
+    fn serialization() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+        let net = random_network(&mut rng);
+
+        let bytes = net.to_bytes();
+        assert_eq!(bytes.len(), Network::BYTES);
+        assert_eq!(Network::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+        assert!(Network::from_bytes(&bytes[1..]).is_none());
+
+        // The AVX2 path would silently disagree with the scalar one on these.
+        let output_weights = (INPUTS * HIDDEN + HIDDEN) * 2;
+        for (w, valid) in [
+            (128, true),
+            (-128, true),
+            (129, false),
+            (-129, false),
+            (i16::MIN, false),
+        ] {
+            let mut bytes = bytes.clone();
+            bytes[output_weights + 2 * HIDDEN..][..2].copy_from_slice(&i16::to_le_bytes(w));
+            assert_eq!(Network::from_bytes(&bytes).is_some(), valid, "{w}");
+        }
+    }
+
+    #[test]
+    fn weight_bounds() {
+        let start = Board::start_pos();
+        let mut net = Network::zeroed();
+
+        // Every accumulator value of the start position is exactly `i16::MAX`.
+        net.feature_weights
+            .iter_mut()
+            .flatten()
+            .for_each(|w| *w = 1000);
+        net.feature_bias = [767; HIDDEN];
+        let net = Network::from_bytes(&net.to_bytes()).unwrap();
+        let acc = Accumulator::new(&net, &start);
+        assert!(acc.values.values().flatten().all(|&v| v == i16::MAX));
+
+        let mut too_large = Network::zeroed();
+        too_large
+            .feature_weights
+            .iter_mut()
+            .flatten()
+            .for_each(|w| *w = 1000);
+        too_large.feature_bias = [768; HIDDEN];
+        assert!(Network::from_bytes(&too_large.to_bytes()).is_none());
+        too_large.feature_bias = [0; HIDDEN];
+        too_large
+            .feature_weights
+            .iter_mut()
+            .flatten()
+            .for_each(|w| *w = 2000);
+        assert!(Network::from_bytes(&too_large.to_bytes()).is_none());
+
+        // All activations at `QA` with the largest output weights and bias.
+        let mut net = Network::zeroed();
+        net.feature_weights
+            .iter_mut()
+            .flatten()
+            .for_each(|w| *w = 8);
+        net.output_weights = [[128; HIDDEN]; 2];
+        net.output_bias = i16::MAX;
+        let net = Network::from_bytes(&net.to_bytes()).unwrap();
+        let acc = Accumulator::new(&net, &start);
+        let expected = (2 * HIDDEN as i64 * 128 * QA as i64 + i16::MAX as i64) * SCALE as i64
+            / (QA * QB) as i64;
+        assert_eq!(net.evaluate(&acc, Color::White), expected as i32);
+        assert_eq!(net.evaluate_scalar(&acc, Color::White), expected as i32);
+    }
+
+    #[test]
+    fn default_network() {
+        // The default network is bootstrapped from the piece-square tables, averaged over the
+        // game phase.
+        for fen in FENS {
+            let board = Board::read_fen(fen).unwrap();
+            let acc = Accumulator::new(&DEFAULT_NETWORK, &board);
+            let psqt = (board.psqt.mg + board.psqt.eg) / 2;
+            let psqt = match board.stm {
+                Color::White => psqt,
+                Color::Black => -psqt,
+            };
+
+            let eval = DEFAULT_NETWORK.evaluate(&acc, board.stm);
+            assert!(
+                (eval - psqt).abs() <= psqt.abs() / 50 + 2,
+                "{fen}: {eval} vs {psqt}"
+            );
+        }
+
+        let start = Board::start_pos();
+        let acc = Accumulator::new(&DEFAULT_NETWORK, &start);
+        assert_eq!(DEFAULT_NETWORK.evaluate(&acc, start.stm), 0);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::*;
+
+/// Gets told about every piece `Board::make_move_with` adds, removes or moves, so that
+/// incremental state living outside of the board can follow along without diffing boards.
+/// Additions and removals default to doing nothing and a move defaults to a removal followed by
+/// an addition, so observing a move with `()` costs nothing.
+///
+/// A capture first reports the victim as removed. Castling reports both the king and the rook
+/// as moved, where in Chess960 `from` and `to` may be the same square, and en passant reports
+/// the captured pawn as removed after moving the capturing one. Promotions remove the pawn and
+/// add the promoted piece.
+pub trait MoveObserver {
+    #[inline(always)]
+    fn piece_added(&mut self, _sq: Square, _pt: PieceType, _color: Color) {}
+
+    #[inline(always)]
+    fn piece_removed(&mut self, _sq: Square, _pt: PieceType, _color: Color) {}
+
+    #[inline(always)]
+    fn piece_moved(&mut self, from: Square, to: Square, pt: PieceType, color: Color) {
+        self.piece_removed(from, pt, color);
+        self.piece_added(to, pt, color);
+    }
+}
+
+impl MoveObserver for () {}
+
+/// A piece that was added to or removed from a square.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PieceUpdate {
+    pub sq: Square,
+    pub pt: PieceType,
+    pub color: Color,
+    pub added: bool,
+}
+
+/// Collects the pieces added and removed by a single move, in the order it happened. No move
+/// touches more than four squares (castling moves both the king and the rook).
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PieceUpdates {
+    updates: [Option<PieceUpdate>; 4],
+    len: usize,
+}
+
+impl PieceUpdates {
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = PieceUpdate> + '_ {
+        self.updates[..self.len].iter().map(|u| u.unwrap())
+    }
+
+    #[inline]
+    fn push(&mut self, sq: Square, pt: PieceType, color: Color, added: bool) {
+        self.updates[self.len] = Some(PieceUpdate {
+            sq,
+            pt,
+            color,
+            added,
+        });
+        self.len += 1;
+    }
+}
+
+impl MoveObserver for PieceUpdates {
+    #[inline]
+    fn piece_added(&mut self, sq: Square, pt: PieceType, color: Color) {
+        self.push(sq, pt, color, true);
+    }
+
+    #[inline]
+    fn piece_removed(&mut self, sq: Square, pt: PieceType, color: Color) {
+        self.push(sq, pt, color, false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enum_map::EnumMap;
+    use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+    /// Keeps its own copy of the board's pieces, maintained purely from the reported events.
+    /// During a Chess960 castle, the king may briefly share a square with its rook, so every
+    /// square holds a list of pieces.
+    struct Mirror {
+        pieces: EnumMap<Square, Vec<(PieceType, Color)>>,
+        events: Vec<String>,
+    }
+
+    impl Mirror {
+        fn new(board: &Board) -> Self {
+            Self {
+                pieces: EnumMap::from_fn(|sq| {
+                    let color = Color::from_idx(board.occupied[Color::Black].contains(sq) as u8);
+                    board
+                        .piece_on(sq)
+                        .map(|pt| (pt, color))
+                        .into_iter()
+                        .collect()
+                }),
+                events: vec![],
+            }
+        }
+
+        fn remove(&mut self, sq: Square, pt: PieceType, color: Color) {
+            let idx = self.pieces[sq].iter().position(|&p| p == (pt, color));
+            self.pieces[sq].remove(idx.expect("Removed a piece that isn't there"));
+        }
+    }
+
+    impl MoveObserver for Mirror {
+        fn piece_added(&mut self, sq: Square, pt: PieceType, color: Color) {
+            self.pieces[sq].push((pt, color));
+            self.events.push(format!("+{}{sq:?}", pt.to_char(color)));
+        }
+
+        fn piece_removed(&mut self, sq: Square, pt: PieceType, color: Color) {
+            self.remove(sq, pt, color);
+            self.events.push(format!("-{}{sq:?}", pt.to_char(color)));
+        }
+
+        fn piece_moved(&mut self, from: Square, to: Square, pt: PieceType, color: Color) {
+            self.remove(from, pt, color);
+            self.pieces[to].push((pt, color));
+            self.events
+                .push(format!("{}{from:?}{to:?}", pt.to_char(color)));
+        }
+    }
+
+    fn events(fen: &str, mov: &str) -> Vec<String> {
+        let mut board = Board::read_fen(fen).unwrap();
+        let mov = board.parse_move(mov, true).unwrap();
+        let mut mirror = Mirror::new(&board);
+        board.make_move_with(mov, &mut mirror);
+        mirror.events
+    }
+
+    #[test]
+    fn special_moves() {
+        const KIWIPETE: &str =
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+        assert_eq!(events(KIWIPETE, "e5f7"), ["-pF7", "NE5F7"]);
+        assert_eq!(events(KIWIPETE, "e1h1"), ["KE1G1", "RH1F1"]);
+        assert_eq!(events(KIWIPETE, "e1a1"), ["KE1C1", "RA1D1"]);
+        assert_eq!(
+            events("4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 1", "d5e6"),
+            ["PD5E6", "-pE5"]
+        );
+        assert_eq!(
+            events("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q"),
+            ["-rB8", "-PA7", "+QB8"]
+        );
+    }
+
+    #[test]
+    fn random_playouts() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9",
+            "rkr5/8/8/8/8/8/8/RKR5 w CAca - 0 1",
+        ] {
+            for _ in 0..20 {
+                let mut board = Board::read_fen(fen).unwrap();
+                let mut mirror = Mirror::new(&board);
+
+                for _ in 0..100 {
+                    let mut moves = vec![];
+                    board.gen_moves(|m| moves.extend(m));
+                    if moves.is_empty() || board.halfmove_clock >= 99 {
+                        break;
+                    }
+
+                    board.make_move_with(moves[rng.random_range(0..moves.len())], &mut mirror);
+                    assert_eq!(mirror.pieces, Mirror::new(&board).pieces);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use crate::*;
+
+/// The 4-bit code of a piece in a packed board is its piece type index, plus 8 if it's black.
+/// Two extra codes mark pieces that carry state FEN stores separately: a rook its king may
+/// still castle with, and a pawn that just moved two squares and may be taken en passant.
+const CASTLING_ROOK: u8 = 6;
+const EN_PASSANT_PAWN: u8 = 7;
+const BLACK: u8 = 8;
+
+impl Board {
+    /// Packs the board into 32 bytes, preserving everything `fen` does:
+    ///
+    /// Bytes 0-7:   Occupancy bitboard, little endian
+    /// Bytes 8-23:  A 4-bit code per occupied square in ascending square order, low nibble first
+    /// Byte 24:     Side to move (0 for white)
+    /// Byte 25:     Halfmove clock
+    /// Bytes 26-29: Fullmove count, little endian
+    /// Bytes 30-31: Zero
+    ///
+    /// Since castling rooks and the en passant pawn have codes of their own, castling rights
+    /// work for any rook files, as in (Double) Chess960.
+    pub fn pack(&self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        bytes[0..8].copy_from_slice(&self.occupied().0.to_le_bytes());
+
+        let ep_pawn = self
+            .en_passant
+            .map(|f| Square::from_file_rank(f, Rank::R4.relative_to(!self.stm)));
+
+        for (i, sq) in self.occupied().into_iter().enumerate() {
+            let color = Color::from_idx(self.occupied[Color::Black].contains(sq) as u8);
+            let pt = self.piece_on(sq).unwrap();
+
+            let rights = self.castles[color];
+            let castling_rook = pt == PieceType::Rook
+                && sq.rank() == Rank::R1.relative_to(color)
+                && [rights.short, rights.long].contains(&Some(sq.file()));
+
+            let code = if castling_rook {
+                CASTLING_ROOK
+            } else if Some(sq) == ep_pawn {
+                EN_PASSANT_PAWN
+            } else {
+                pt.idx()
+            };
+            bytes[8 + i / 2] |= (code | (color.idx() * BLACK)) << (i % 2 * 4);
+        }
+
+        bytes[24] = self.stm.idx();
+        bytes[25] = self.halfmove_clock;
+        bytes[26..30].copy_from_slice(&self.fullmove_count.to_le_bytes());
+        bytes
+    }
+
+    /// Unpacks a board packed by `pack`, or returns `None` if the bytes don't describe a
+    /// position `read_fen` would accept.
+    pub fn unpack(bytes: &[u8; 32]) -> Option<Self> {
+        let occupied = Bitboard(u64::from_le_bytes(bytes[0..8].try_into().unwrap()));
+        if occupied.popcnt() > 32 || bytes[30..32] != [0, 0] {
+            return None;
+        }
+
+        let mut builder = BoardBuilder::new();
+        let stm = match bytes[24] {
+            0 => Color::White,
+            1 => Color::Black,
+            _ => return None,
+        };
+        builder.set_side_to_move(stm);
+        builder.set_halfmove_clock(bytes[25]);
+        builder.set_fullmove_count(u32::from_le_bytes(bytes[26..30].try_into().unwrap()));
+
+        let mut castling_rooks = vec![];
+        for (i, sq) in occupied.into_iter().enumerate() {
+            let code = (bytes[8 + i / 2] >> (i % 2 * 4)) & 0xf;
+            let color = Color::from_idx(code / BLACK);
+
+            let pt = match code % BLACK {
+                CASTLING_ROOK => {
+                    castling_rooks.push((sq, color));
+                    PieceType::Rook
+                }
+                EN_PASSANT_PAWN => {
+                    if color == stm
+                        || sq.rank() != Rank::R4.relative_to(color)
+                        || builder.en_passant().is_some()
+                    {
+                        return None;
+                    }
+                    builder.set_en_passant(Some(sq.file()));
+                    PieceType::Pawn
+                }
+                idx => PieceType::from_idx(idx),
+            };
+
+            builder.put(sq, color, pt);
+        }
+
+        for (sq, color) in castling_rooks {
+            let king = builder.king(color)?;
+            let short = sq.file() > king.file();
+            let rights = builder.castling(color);
+
+            if sq.rank() != king.rank()
+                || sq.rank() != Rank::R1.relative_to(color)
+                || (if short { rights.short } else { rights.long }).is_some()
+            {
+                return None;
+            }
+            builder.set_castling(color, short, Some(sq.file()));
+        }
+
+        // Checks the kings, the clocks and everything else `read_fen` does.
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+    fn assert_round_trip(board: &Board) {
+        let unpacked = Board::unpack(&board.pack()).unwrap();
+
+        assert_eq!(unpacked.fen(true), board.fen(true));
+        assert_eq!(unpacked.hash, board.hash);
+        assert_eq!(unpacked.pawn_hash, board.pawn_hash);
+        assert_eq!(unpacked.pinned, board.pinned);
+        assert_eq!(unpacked.checkers, board.checkers);
+        assert_eq!(unpacked.mailbox, board.mailbox);
+    }
+
+    fn perft960_positions() -> impl Iterator<Item = Board> {
+        include_str!("../perft960.txt")
+            .lines()
+            .map(|line| Board::read_fen(line.split('\t').nth(1).unwrap().trim()).unwrap())
+    }
+
+    #[test]
+    fn round_trip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 2",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 99 123456",
+            "rkr5/8/8/8/8/8/8/RKR5 w CAca - 0 1",
+        ] {
+            assert_round_trip(&Board::read_fen(fen).unwrap());
+        }
+
+        for board in perft960_positions() {
+            assert_round_trip(&board);
+        }
+    }
+
+    #[test]
+    fn random_playouts() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+
+        for start in perft960_positions().step_by(4) {
+            for _ in 0..5 {
+                let mut board = start;
+                for _ in 0..100 {
+                    assert_round_trip(&board);
+
+                    let mut moves = vec![];
+                    board.gen_moves(|m| moves.extend(m));
+                    if moves.is_empty() || board.halfmove_clock >= 99 {
+                        break;
+                    }
+                    board.make_move(moves[rng.random_range(0..moves.len())]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        let packed = Board::start_pos().pack();
+
+        let mut no_king = packed;
+        no_king[8 + 2] = 0x11;
+        assert!(Board::unpack(&no_king).is_none());
+
+        let mut bad_stm = packed;
+        bad_stm[24] = 2;
+        assert!(Board::unpack(&bad_stm).is_none());
+
+        let mut bad_clock = packed;
+        bad_clock[25] = 100;
+        assert!(Board::unpack(&bad_clock).is_none());
+
+        // An en passant pawn of the side to move, on the first rank.
+        let mut bad_ep = packed;
+        bad_ep[8] = (bad_ep[8] & 0xf0) | EN_PASSANT_PAWN;
+        assert!(Board::unpack(&bad_ep).is_none());
+
+        // Three castling rooks for white.
+        let mut extra_rook = packed;
+        extra_rook[9] = (extra_rook[9] & 0xf0) | CASTLING_ROOK;
+        assert!(Board::unpack(&extra_rook).is_none());
+
+        // A black pawn on a8.
+        let mut back_rank_pawn = packed;
+        back_rank_pawn[20] = (back_rank_pawn[20] & 0xf0) | PieceType::Pawn.idx() | BLACK;
+        assert!(Board::unpack(&back_rank_pawn).is_none());
+
+        // The side not to move is in check.
+        let mut in_check = Board::read_fen("4k3/8/8/8/8/8/8/4R1K1 b - - 0 1")
+            .unwrap()
+            .pack();
+        in_check[24] = 0;
+        assert!(Board::unpack(&in_check).is_none());
+
+        // The en passant pawn on e4 can't have come from e2, since there is a knight on it.
+        let mut blocked_ep = Board::read_fen("4k3/8/8/8/3pP3/8/4N3/4K3 b - - 0 1")
+            .unwrap()
+            .pack();
+        blocked_ep[9] = (blocked_ep[9] & 0x0f) | EN_PASSANT_PAWN << 4;
+        assert!(Board::unpack(&blocked_ep).is_none());
+
+        // Boards with more than 32 pieces can't be packed, so they must not exist.
+        assert!(
+            Board::read_fen(
+                "nnnnknnn/pppppppp/pppppppp/pppppppp/PPPPPPPP/PPPPPPPP/PPPPPPPP/NNNNKNNN w - - 0 1"
+            )
+            .is_none()
+        );
+        assert!(
+            Board::read_fen("nnnnknnn/pppppppp/n7/8/8/8/PPPPPPPP/NNNNKNNN w - - 0 1").is_none()
+        );
+        assert_round_trip(
+            &Board::read_fen("nnnnknnn/pppppppp/8/8/8/8/PPPPPPPP/NNNNKNNN w - - 0 1").unwrap(),
+        );
+
+        assert!(Board::unpack(&[0; 32]).is_none());
+        assert!(Board::unpack(&[0xff; 32]).is_none());
+    }
+}
@@ -0,0 +1,305 @@
+//! Evaluation weights. The initial values are Ronald Friederich's PeSTO tables, until we have
+//! tuned our own.
+
+use crate::*;
+
+/// Indexed by `PieceType::idx()`.
+pub const MATERIAL: [Score; PieceType::COUNT] = [
+    Score::new(337, 281),
+    Score::new(365, 297),
+    Score::new(477, 512),
+    Score::new(1025, 936),
+    Score::new(82, 94),
+    Score::new(0, 0),
+];
+
+/// How much each piece type contributes to the game phase. The phase of the starting position
+/// is `MAX_PHASE`, while a position with only pawns and kings has phase 0.
+pub const PHASE_WEIGHTS: [i32; PieceType::COUNT] = [1, 1, 2, 4, 0, 0];
+pub const MAX_PHASE: i32 = 24;
+
+/// Bonus for passed pawns, indexed by their rank relative to their owner.
+pub const PASSED: [Score; Rank::COUNT] = [
+    Score::new(0, 0),
+    Score::new(0, 5),
+    Score::new(-5, 10),
+    Score::new(-5, 25),
+    Score::new(15, 45),
+    Score::new(45, 90),
+    Score::new(90, 150),
+    Score::new(0, 0),
+];
+
+/// Bonus for pawns that are defended by or next to another pawn, indexed by relative rank.
+pub const CONNECTED: [Score; Rank::COUNT] = [
+    Score::new(0, 0),
+    Score::new(0, 0),
+    Score::new(7, 4),
+    Score::new(8, 6),
+    Score::new(14, 12),
+    Score::new(30, 28),
+    Score::new(55, 50),
+    Score::new(0, 0),
+];
+
+pub const ISOLATED: Score = Score::new(-8, -12);
+pub const DOUBLED: Score = Score::new(-10, -25);
+pub const BACKWARD: Score = Score::new(-6, -10);
+
+/// Per square of distance between a passed pawn's stop square and its owner's king, indexed
+/// by the pawn's relative rank.
+pub const PASSER_OWN_KING_DISTANCE: [Score; Rank::COUNT] = [
+    Score::new(0, 0),
+    Score::new(0, 0),
+    Score::new(0, 0),
+    Score::new(0, -3),
+    Score::new(0, -6),
+    Score::new(0, -9),
+    Score::new(0, -12),
+    Score::new(0, 0),
+];
+
+/// Per square of distance between a passed pawn's stop square and the enemy king.
+pub const PASSER_THEIR_KING_DISTANCE: [Score; Rank::COUNT] = [
+    Score::new(0, 0),
+    Score::new(0, 0),
+    Score::new(0, 0),
+    Score::new(0, 6),
+    Score::new(0, 12),
+    Score::new(0, 18),
+    Score::new(0, 24),
+    Score::new(0, 0),
+];
+
+/// Mobility bonuses, indexed by the number of squares a piece attacks that are neither
+/// occupied by our own pieces nor attacked by enemy pawns.
+#[rustfmt::skip]
+pub const KNIGHT_MOBILITY: [Score; 9] = table(
+    [-30, -20,  -8,  -2,   3,   8,  13,  17,  20],
+    [-40, -28, -14,  -6,   2,   8,  13,  16,  18],
+);
+
+#[rustfmt::skip]
+pub const BISHOP_MOBILITY: [Score; 14] = table(
+    [-25, -15,  -5,   0,   5,  10,  14,  17,  20,  22,  24,  26,  28,  30],
+    [-35, -22, -10,  -3,   4,   9,  14,  18,  21,  24,  26,  28,  30,  31],
+);
+
+#[rustfmt::skip]
+pub const ROOK_MOBILITY: [Score; 15] = table(
+    [-18, -12,  -7,  -3,   0,   2,   4,   7,  10,  12,  14,  16,  18,  19,  20],
+    [-35, -20,  -8,   0,   6,  12,  18,  23,  28,  32,  36,  39,  42,  44,  45],
+);
+
+#[rustfmt::skip]
+pub const QUEEN_MOBILITY: [Score; 28] = table(
+    [
+        -12,  -9,  -6,  -4,  -2,   0,   1,   2,   4,   5,   6,   7,   8,   9,
+         10,  11,  12,  13,  13,  14,  14,  15,  15,  16,  16,  17,  17,  18,
+    ],
+    [
+        -25, -18, -12,  -8,  -4,   0,   4,   8,  12,  15,  18,  21,  24,  26,
+         28,  30,  32,  33,  34,  35,  36,  37,  38,  38,  39,  39,  40,  40,
+    ],
+);
+
+/// How many attack units a piece contributes per attacked square in the enemy king zone,
+/// indexed by `PieceType::idx()`.
+pub const KING_ATTACK_WEIGHT: [usize; PieceType::COUNT] = [2, 2, 3, 5, 0, 0];
+
+/// Bonus for attacking the enemy king, indexed by attack units. It grows quadratically, since
+/// attacks by several pieces at once are much harder to defend than the sum of their parts.
+pub const KING_ATTACK: [Score; 40] = {
+    let mut res = [Score::ZERO; 40];
+    let mut i = 0;
+    while i < res.len() {
+        let units = i as i32;
+        res[i] = Score::new(units * units / 3, units * units / 12);
+        i += 1;
+    }
+    res
+};
+
+/// Indexed by the relative rank of our pawn closest to the king on one of the three files
+/// around it, where 0 means there is no such pawn.
+pub const SHELTER: [Score; Rank::COUNT] = [
+    Score::new(-30, 0),
+    Score::new(12, 0),
+    Score::new(6, 0),
+    Score::new(-6, 0),
+    Score::new(-14, 0),
+    Score::new(-20, 0),
+    Score::new(-25, 0),
+    Score::new(0, 0),
+];
+
+/// Indexed by the rank (relative to us) of the closest enemy pawn approaching our king on one
+/// of the three files around it, where 0 means there is no such pawn.
+pub const STORM: [Score; Rank::COUNT] = [
+    Score::new(0, 0),
+    Score::new(-5, 0),
+    Score::new(-30, 0),
+    Score::new(-16, 0),
+    Score::new(-6, 0),
+    Score::new(-2, 0),
+    Score::new(0, 0),
+    Score::new(0, 0),
+];
+
+/// Penalties for files next to the king without any of our pawns.
+pub const KING_OPEN_FILE: Score = Score::new(-25, 0);
+pub const KING_SEMI_OPEN_FILE: Score = Score::new(-12, 0);
+
+/// Piece-square tables from white's point of view, indexed by `PieceType::idx()` and then by
+/// square. The tables are written as seen from white's side of the board, so rank 8 comes first.
+#[rustfmt::skip]
+pub const PST: [[Score; Square::COUNT]; PieceType::COUNT] = [
+    // Knight
+    table(
+        [
+            -167, -89, -34, -49,  61, -97, -15,-107,
+             -73, -41,  72,  36,  23,  62,   7, -17,
+             -47,  60,  37,  65,  84, 129,  73,  44,
+              -9,  17,  19,  53,  37,  69,  18,  22,
+             -13,   4,  16,  13,  28,  19,  21,  -8,
+             -23,  -9,  12,  10,  19,  17,  25, -16,
+             -29, -53, -12,  -3,  -1,  18, -14, -19,
+            -105, -21, -58, -33, -17, -28, -19, -23,
+        ],
+        [
+             -58, -38, -13, -28, -31, -27, -63, -99,
+             -25,  -8, -25,  -2,  -9, -25, -24, -52,
+             -24, -20,  10,   9,  -1,  -9, -19, -41,
+             -17,   3,  22,  22,  22,  11,   8, -18,
+             -18,  -6,  16,  25,  16,  17,   4, -18,
+             -23,  -3,  -1,  15,  10,  -3, -20, -22,
+             -42, -20, -10,  -5,  -2, -20, -23, -44,
+             -29, -51, -23, -15, -22, -18, -50, -64,
+        ],
+    ),
+    // Bishop
+    table(
+        [
+             -29,   4, -82, -37, -25, -42,   7,  -8,
+             -26,  16, -18, -13,  30,  59,  18, -47,
+             -16,  37,  43,  40,  35,  50,  37,  -2,
+              -4,   5,  19,  50,  37,  37,   7,  -2,
+              -6,  13,  13,  26,  34,  12,  10,   4,
+               0,  15,  15,  15,  14,  27,  18,  10,
+               4,  15,  16,   0,   7,  21,  33,   1,
+             -33,  -3, -14, -21, -13, -12, -39, -21,
+        ],
+        [
+             -14, -21, -11,  -8,  -7,  -9, -17, -24,
+              -8,  -4,   7, -12,  -3, -13,  -4, -14,
+               2,  -8,   0,  -1,  -2,   6,   0,   4,
+              -3,   9,  12,   9,  14,  10,   3,   2,
+              -6,   3,  13,  19,   7,  10,  -3,  -9,
+             -12,  -3,   8,  10,  13,   3,  -7, -15,
+             -14, -18,  -7,  -1,   4,  -9, -15, -27,
+             -23,  -9, -23,  -5,  -9, -16,  -5, -17,
+        ],
+    ),
+    // Rook
+    table(
+        [
+              32,  42,  32,  51,  63,   9,  31,  43,
+              27,  32,  58,  62,  80,  67,  26,  44,
+              -5,  19,  26,  36,  17,  45,  61,  16,
+             -24, -11,   7,  26,  24,  35,  -8, -20,
+             -36, -26, -12,  -1,   9,  -7,   6, -23,
+             -45, -25, -16, -17,   3,   0,  -5, -33,
+             -44, -16, -20,  -9,  -1,  11,  -6, -71,
+             -19, -13,   1,  17,  16,   7, -37, -26,
+        ],
+        [
+              13,  10,  18,  15,  12,  12,   8,   5,
+              11,  13,  13,  11,  -3,   3,   8,   3,
+               7,   7,   7,   5,   4,  -3,  -5,  -3,
+               4,   3,  13,   1,   2,   1,  -1,   2,
+               3,   5,   8,   4,  -5,  -6,  -8, -11,
+              -4,   0,  -5,  -1,  -7, -12,  -8, -16,
+              -6,  -6,   0,   2,  -9,  -9, -11,  -3,
+              -9,   2,   3,  -1,  -5, -13,   4, -20,
+        ],
+    ),
+    // Queen
+    table(
+        [
+             -28,   0,  29,  12,  59,  44,  43,  45,
+             -24, -39,  -5,   1, -16,  57,  28,  54,
+             -13, -17,   7,   8,  29,  56,  47,  57,
+             -27, -27, -16, -16,  -1,  17,  -2,   1,
+              -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+             -14,   2, -11,  -2,  -5,   2,  14,   5,
+             -35,  -8,  11,   2,   8,  15,  -3,   1,
+              -1, -18,  -9,  10, -15, -25, -31, -50,
+        ],
+        [
+              -9,  22,  22,  27,  27,  19,  10,  20,
+             -17,  20,  32,  41,  58,  25,  30,   0,
+             -20,   6,   9,  49,  47,  35,  19,   9,
+               3,  22,  24,  45,  57,  40,  57,  36,
+             -18,  28,  19,  47,  31,  34,  39,  23,
+             -16, -27,  15,   6,   9,  17,  10,   5,
+             -22, -23, -30, -16, -16, -23, -36, -32,
+             -33, -28, -22, -43,  -5, -32, -20, -41,
+        ],
+    ),
+    // Pawn
+    table(
+        [
+               0,   0,   0,   0,   0,   0,   0,   0,
+              98, 134,  61,  95,  68, 126,  34, -11,
+              -6,   7,  26,  31,  65,  56,  25, -20,
+             -14,  13,   6,  21,  23,  12,  17, -23,
+             -27,  -2,  -5,  12,  17,   6,  10, -25,
+             -26,  -4,  -4, -10,   3,   3,  33, -12,
+             -35,  -1, -20, -23, -15,  24,  38, -22,
+               0,   0,   0,   0,   0,   0,   0,   0,
+        ],
+        [
+               0,   0,   0,   0,   0,   0,   0,   0,
+             178, 173, 158, 134, 147, 132, 165, 187,
+              94, 100,  85,  67,  56,  53,  82,  84,
+              32,  24,  13,   5,  -2,   4,  17,  17,
+              13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+               4,   7,  -6,   1,   0,  -5,  -1,  -8,
+              13,   8,   8,  10,  13,   0,   2,  -7,
+               0,   0,   0,   0,   0,   0,   0,   0,
+        ],
+    ),
+    // King
+    table(
+        [
+             -65,  23,  16, -15, -56, -34,   2,  13,
+              29,  -1, -20,  -7,  -8,  -4, -38, -29,
+              -9,  24,   2, -16, -20,   6,  22, -22,
+             -17, -20, -12, -27, -30, -25, -14, -36,
+             -49,  -1, -27, -39, -46, -44, -33, -51,
+             -14, -14, -22, -46, -44, -30, -15, -27,
+               1,   7,  -8, -64, -43, -16,   9,   8,
+             -15,  36,  12, -54,   8, -28,  24,  14,
+        ],
+        [
+             -74, -35, -18, -18, -11,  15,   4, -17,
+             -12,  17,  14,  17,  17,  38,  23,  11,
+              10,  17,  23,  15,  20,  45,  44,  13,
+              -8,  22,  24,  27,  26,  33,  26,   3,
+             -18,  -4,  21,  24,  27,  23,   9, -11,
+             -19,  -3,  11,  21,  23,  16,   7,  -9,
+             -27, -11,   4,  13,  14,   4,  -5, -17,
+             -53, -34, -21, -11, -28, -14, -24, -43,
+        ],
+    ),
+];
+
+const fn table<const N: usize>(mg: [i32; N], eg: [i32; N]) -> [Score; N] {
+    let mut res = [Score::ZERO; N];
+    let mut i = 0;
+    while i < N {
+        res[i] = Score::new(mg[i], eg[i]);
+        i += 1;
+    }
+    res
+}
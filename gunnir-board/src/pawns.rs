@@ -0,0 +1,242 @@
+use crate::*;
+
+/// Everything we know about a pawn structure, independent of the other pieces.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PawnEntry {
+    key: u64,
+    /// Pawn structure score from white's point of view.
+    pub score: Score,
+    /// Passed pawns of both colors.
+    pub passed: Bitboard,
+}
+
+impl PawnEntry {
+    #[inline]
+    pub fn compute(board: &Board) -> Self {
+        Self::compute_traced(board, &mut ())
+    }
+
+    pub fn compute_traced<T: Tracer>(board: &Board, tracer: &mut T) -> Self {
+        let mut entry = Self {
+            key: board.pawn_hash,
+            score: Score::ZERO,
+            passed: Bitboard::EMPTY,
+        };
+
+        for color in Color::ALL {
+            let score = entry.add_pawn_terms(board, *color, tracer);
+            tracer.trace(Term::Pawns, *color, score);
+            entry.score += match color {
+                Color::White => score,
+                Color::Black => -score,
+            };
+        }
+
+        entry
+    }
+
+    fn add_pawn_terms<T: Tracer>(&mut self, board: &Board, color: Color, tracer: &mut T) -> Score {
+        let ours = board.colored_pieces(PieceType::Pawn, color);
+        let theirs = board.colored_pieces(PieceType::Pawn, !color);
+
+        let mut score = Score::ZERO;
+
+        for sq in ours {
+            let rank = sq.rank().relative_to(color).idx() as usize;
+            let file = sq.bitboard().file_fill();
+            let adjacent = adjacent_files(file);
+
+            let front = front_span(sq.bitboard(), color);
+            let behind = match color {
+                Color::White => sq.rank().bitboard().fill_down(),
+                Color::Black => sq.rank().bitboard().fill_up(),
+            };
+
+            let supported = (pawn_attacks(sq, !color) & ours).is_non_empty();
+            let phalanx = (adjacent & sq.rank().bitboard() & ours).is_non_empty();
+
+            if (theirs & (front | adjacent_files(front))).is_empty() {
+                self.passed |= sq;
+                score += tracer.weight(ParamGroup::Passed, rank, color, 1);
+            }
+
+            if (ours & front).is_non_empty() {
+                score += tracer.weight(ParamGroup::Doubled, 0, color, 1);
+            }
+
+            if (ours & adjacent).is_empty() {
+                score += tracer.weight(ParamGroup::Isolated, 0, color, 1);
+            } else if supported || phalanx {
+                score += tracer.weight(ParamGroup::Connected, rank, color, 1);
+            } else {
+                // A pawn is backward if no neighbouring pawn can ever defend it, and it can't
+                // advance safely either.
+                let stop = sq.offset(0, color.signum());
+                if (ours & adjacent & behind).is_empty()
+                    && (pawn_attacks(stop, color) & theirs).is_non_empty()
+                {
+                    score += tracer.weight(ParamGroup::Backward, 0, color, 1);
+                }
+            }
+        }
+
+        score
+    }
+
+    /// Scores how close both kings are to each passed pawn. This depends on the king positions,
+    /// so unlike the rest of the pawn evaluation it can't be cached in the pawn hash table.
+    pub fn passer_king_proximity<T: Tracer>(&self, board: &Board, tracer: &mut T) -> Score {
+        let mut score = Score::ZERO;
+
+        for color in Color::ALL {
+            let (our_king, their_king) = (board.king(*color), board.king(!*color));
+
+            for sq in self.passed & board.occupied[*color] {
+                let rank = sq.rank().relative_to(*color).idx() as usize;
+                let stop = sq.offset(0, color.signum());
+
+                let (ours, theirs) = (our_king.distance(stop), their_king.distance(stop));
+                let term =
+                    tracer.weight(ParamGroup::PasserOwnKingDistance, rank, *color, ours as i32)
+                        + tracer.weight(
+                            ParamGroup::PasserTheirKingDistance,
+                            rank,
+                            *color,
+                            theirs as i32,
+                        );
+                tracer.trace(Term::PasserKingProximity, *color, term);
+
+                score += match color {
+                    Color::White => term,
+                    Color::Black => -term,
+                };
+            }
+        }
+
+        score
+    }
+}
+
+/// All squares in front of the given pawns, from their owner's point of view.
+#[inline]
+pub fn front_span(pawns: Bitboard, color: Color) -> Bitboard {
+    match color {
+        Color::White => pawns.shift::<Up>(1).fill_up(),
+        Color::Black => pawns.shift::<Down>(1).fill_down(),
+    }
+}
+
+/// All squares attacked by the given pawns.
+#[inline]
+pub fn all_pawn_attacks(pawns: Bitboard, color: Color) -> Bitboard {
+    match color {
+        Color::White => pawns.shift::<UpLeft>(1) | pawns.shift::<UpRight>(1),
+        Color::Black => pawns.shift::<DownLeft>(1) | pawns.shift::<DownRight>(1),
+    }
+}
+
+#[inline]
+fn adjacent_files(bb: Bitboard) -> Bitboard {
+    bb.shift::<Left>(1) | bb.shift::<Right>(1)
+}
+
+/// Pawn structures change rarely during search, so we cache their evaluation keyed by
+/// `Board::pawn_hash`.
+pub struct PawnTable {
+    entries: Vec<PawnEntry>,
+}
+
+impl PawnTable {
+    pub fn new(len: usize) -> Self {
+        Self {
+            // The empty board has a pawn hash of 0, so we can't use that to mark empty entries.
+            entries: vec![
+                PawnEntry {
+                    key: u64::MAX,
+                    ..Default::default()
+                };
+                len.max(1)
+            ],
+        }
+    }
+
+    #[inline]
+    pub fn probe(&mut self, board: &Board) -> PawnEntry {
+        let idx = ((board.pawn_hash as u128 * self.entries.len() as u128) >> 64) as usize;
+        let entry = &mut self.entries[idx];
+
+        if entry.key != board.pawn_hash {
+            *entry = PawnEntry::compute(board);
+        }
+
+        *entry
+    }
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self::new(1 << 14)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pawn_score(fen: &str) -> Score {
+        PawnEntry::compute(&Board::read_fen(fen).unwrap()).score
+    }
+
+    #[test]
+    fn pawn_terms() {
+        assert_eq!(
+            pawn_score("4k3/8/8/8/8/8/P7/4K3 w - - 0 1"),
+            PASSED[1] + ISOLATED
+        );
+        assert_eq!(
+            pawn_score("4k3/8/8/8/8/P7/P7/4K3 w - - 0 1"),
+            PASSED[1] + PASSED[2] + ISOLATED * 2 + DOUBLED
+        );
+        assert_eq!(
+            pawn_score("4k3/8/8/8/8/1P6/P7/4K3 w - - 0 1"),
+            PASSED[1] + PASSED[2] + CONNECTED[2]
+        );
+        // b2 is backward, and black's c4 pawn isn't passed since it still has to get past b2.
+        assert_eq!(
+            pawn_score("4k3/8/8/8/P1p5/8/1P6/4K3 w - - 0 1"),
+            PASSED[3] + BACKWARD - ISOLATED
+        );
+    }
+
+    #[test]
+    fn pawn_table() {
+        let mut table = PawnTable::new(16);
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        ] {
+            let board = Board::read_fen(fen).unwrap();
+            let expected = PawnEntry::compute(&board);
+            for _ in 0..2 {
+                let entry = table.probe(&board);
+                assert_eq!(
+                    (entry.score, entry.passed),
+                    (expected.score, expected.passed)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn front_spans() {
+        assert_eq!(
+            front_span(Square::E4.bitboard(), Color::White),
+            Square::E5.bitboard() | Square::E6 | Square::E7 | Square::E8
+        );
+        assert_eq!(
+            front_span(Square::E4.bitboard(), Color::Black),
+            Square::E3.bitboard() | Square::E2 | Square::E1
+        );
+    }
+}
@@ -1,6 +1,13 @@
-use crate::Board;
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
 
-fn perft(board: &Board, depth: u8) -> u64 {
+use crate::*;
+
+/// Counts the leaf nodes of the legal move tree of `board` at the given depth.
+pub fn perft(board: &Board, depth: u8) -> u64 {
     let mut nodes = 0;
 
     if depth == 0 {
@@ -25,116 +32,564 @@ fn perft(board: &Board, depth: u8) -> u64 {
     nodes
 }
 
-macro_rules! perft_test {
-        ($name:ident: $board:expr; $($nodes:expr),* $(,)?) => {
-            #[test]
-            fn $name() {
-                const NODES: &'static [u64] = &[$($nodes),*];
+/// Like `perft`, but counts the nodes below every legal move separately, in the order
+/// `gen_moves` generates them. Comparing this with another engine narrows down which move's
+/// subtree a movegen bug is in.
+pub fn divide(board: &Board, depth: u8) -> Vec<(Move, u64)> {
+    let mut counts = vec![];
+    if depth == 0 {
+        return counts;
+    }
+
+    board.gen_moves(|moves| {
+        for mv in moves {
+            let mut child = *board;
+            child.make_move(mv);
+            counts.push((mv, perft(&child, depth - 1)));
+        }
+    });
+
+    counts
+}
+
+/// The work one thread of `parallel_perft` did.
+#[derive(Debug, Clone, Default)]
+pub struct PerftThreadStats {
+    /// The number of subtrees the thread counted.
+    pub tasks: usize,
+    pub nodes: u64,
+    /// The time spent counting, not including waiting for the other threads.
+    pub busy: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParallelPerft {
+    pub nodes: u64,
+    pub threads: Vec<PerftThreadStats>,
+}
+
+fn split_positions(board: &Board, ply: u8, out: &mut Vec<Board>) {
+    if ply == 0 {
+        out.push(*board);
+        return;
+    }
+
+    board.gen_moves(|moves| {
+        for mv in moves {
+            let mut child = *board;
+            child.make_move(mv);
+            split_positions(&child, ply - 1, out);
+        }
+    });
+}
+
+/// Like `perft`, but the subtrees below all positions `split_depth` plies from the root are
+/// handed out to `threads` threads. Splitting at the second ply gives many more, smaller
+/// tasks, which balances the threads better when a few root moves have huge subtrees.
+pub fn parallel_perft(board: &Board, depth: u8, threads: usize, split_depth: u8) -> ParallelPerft {
+    let split_depth = split_depth.min(depth);
+    let mut tasks = vec![];
+    split_positions(board, split_depth, &mut tasks);
+
+    let next = AtomicUsize::new(0);
+    let threads = thread::scope(|s| {
+        let handles: Vec<_> = (0..threads.max(1))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut stats = PerftThreadStats::default();
+                    let start = Instant::now();
+                    while let Some(task) = tasks.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        stats.nodes += perft(task, depth - split_depth);
+                        stats.tasks += 1;
+                    }
+                    stats.busy = start.elapsed();
+                    stats
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    ParallelPerft {
+        nodes: threads.iter().map(|t| t.nodes).sum(),
+        threads,
+    }
+}
+
+/// The breakdown of the nodes at one depth of the move tree, by the move that led to them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerftStats {
+    pub nodes: u64,
+    /// Including en passant captures.
+    pub captures: u64,
+    pub en_passant: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+    /// Single checks by a piece other than the one that moved. Like in the reference tables,
+    /// double checks aren't counted here.
+    pub discovered_checks: u64,
+    pub double_checks: u64,
+    pub checkmates: u64,
+}
+
+impl PerftStats {
+    fn count(&mut self, parent: &Board, mov: Move, child: &Board) {
+        let flag = mov.move_flag();
+        self.nodes += 1;
+        self.captures += (flag == MoveFlag::EnPassant
+            || flag != MoveFlag::Castle && parent.piece_on(mov.to()).is_some())
+            as u64;
+        self.en_passant += (flag == MoveFlag::EnPassant) as u64;
+        self.castles += (flag == MoveFlag::Castle) as u64;
+        self.promotions += (flag == MoveFlag::Promotion) as u64;
+
+        if child.checkers.is_empty() {
+            return;
+        }
+
+        // When castling, only the rook can give check.
+        let checker = if flag == MoveFlag::Castle {
+            let file = if mov.to().file() == File::G {
+                File::F
+            } else {
+                File::D
+            };
+            Square::from_file_rank(file, mov.to().rank())
+        } else {
+            mov.to()
+        };
+
+        self.checks += 1;
+        if child.checkers.popcnt() > 1 {
+            self.double_checks += 1;
+        } else if child.checkers != checker.bitboard() {
+            self.discovered_checks += 1;
+        }
+
+        let mut has_moves = false;
+        child.gen_moves(|_| has_moves = true);
+        self.checkmates += !has_moves as u64;
+    }
+}
+
+fn collect_stats(board: &Board, ply: usize, stats: &mut [PerftStats]) {
+    if ply == stats.len() {
+        return;
+    }
+
+    board.gen_moves(|moves| {
+        for mv in moves {
+            let mut child = *board;
+            child.make_move(mv);
+            stats[ply].count(board, mv, &child);
+            collect_stats(&child, ply + 1, stats);
+        }
+    });
+}
+
+/// Like `perft`, but breaks the nodes at every depth from 1 to `depth` down into the categories
+/// of the reference tables on the Chess Programming Wiki, which helps narrow down movegen bugs.
+/// This makes every move at every depth, so it is much slower than `perft`.
+pub fn perft_stats(board: &Board, depth: u8) -> Vec<PerftStats> {
+    let mut stats = vec![PerftStats::default(); depth as usize];
+    collect_stats(board, 0, &mut stats);
+    stats
+}
+
+#[derive(Clone, Copy, Default)]
+struct PerftEntry {
+    /// The full hash, so that only a real Zobrist collision can return a wrong count.
+    hash: u64,
+    nodes: u64,
+    depth: u8,
+}
+
+/// A fixed-size cache of node counts for `hashed_perft`, indexed by `Board::hash`. Entries are
+/// always replaced.
+pub struct PerftTable {
+    entries: Vec<PerftEntry>,
+}
+
+impl PerftTable {
+    pub fn new(mb: usize) -> Self {
+        let len = (mb * 1024 * 1024 / size_of::<PerftEntry>()).max(1);
+        Self {
+            entries: vec![PerftEntry::default(); len],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(PerftEntry::default());
+    }
+
+    #[inline]
+    fn index(&self, hash: u64) -> usize {
+        ((hash as u128 * self.entries.len() as u128) >> 64) as usize
+    }
+
+    #[inline]
+    pub fn probe(&self, hash: u64, depth: u8) -> Option<u64> {
+        let entry = self.entries[self.index(hash)];
+        // Depth 0 is never stored, which tells empty entries apart.
+        (entry.hash == hash && entry.depth == depth && depth > 0).then_some(entry.nodes)
+    }
+
+    #[inline]
+    pub fn store(&mut self, hash: u64, depth: u8, nodes: u64) {
+        let idx = self.index(hash);
+        self.entries[idx] = PerftEntry { hash, nodes, depth };
+    }
+}
+
+/// Like `perft`, but caches the counts of subtrees in `table`, so transpositions are only
+/// counted once.
+pub fn hashed_perft(board: &Board, depth: u8, table: &mut PerftTable) -> u64 {
+    if depth <= 1 {
+        return perft(board, depth);
+    }
+    if let Some(nodes) = table.probe(board.hash, depth) {
+        return nodes;
+    }
 
-                let board = Board::read_fen($board).unwrap();
-                for (depth, &nodes) in NODES.iter().enumerate() {
-                    assert_eq!(perft(&board, depth as u8 + 1), nodes);
+    let mut nodes = 0;
+    board.gen_moves(|moves| {
+        for mv in moves {
+            let mut child = *board;
+            child.make_move(mv);
+            nodes += hashed_perft(&child, depth - 1, table);
+        }
+    });
+
+    table.store(board.hash, depth, nodes);
+    nodes
+}
+
+/// A cached count that disagrees with the real count of a subtree, found by
+/// `verify_hashed_perft`. Either two positions share a hash, or `make_move` computed a hash
+/// that doesn't match the position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerftMismatch {
+    pub fen: String,
+    pub depth: u8,
+    pub cached: u64,
+    pub actual: u64,
+}
+
+/// Runs `hashed_perft`, but recounts every subtree whose count is found in the table with the
+/// unhashed `perft`, and returns the first one that disagrees. This is much slower than either
+/// on its own, and is meant for testing the hashing.
+pub fn verify_hashed_perft(
+    board: &Board,
+    depth: u8,
+    table: &mut PerftTable,
+) -> Result<u64, PerftMismatch> {
+    if depth <= 1 {
+        return Ok(perft(board, depth));
+    }
+    if let Some(cached) = table.probe(board.hash, depth) {
+        let actual = perft(board, depth);
+        if cached != actual {
+            return Err(PerftMismatch {
+                fen: board.fen(true),
+                depth,
+                cached,
+                actual,
+            });
+        }
+        return Ok(cached);
+    }
+
+    let mut nodes = 0;
+    let mut mismatch = None;
+    board.gen_moves(|moves| {
+        for mv in moves {
+            if mismatch.is_some() {
+                return;
+            }
+            let mut child = *board;
+            child.make_move(mv);
+            match verify_hashed_perft(&child, depth - 1, table) {
+                Ok(n) => nodes += n,
+                Err(e) => mismatch = Some(e),
+            }
+        }
+    });
+
+    if let Some(mismatch) = mismatch {
+        return Err(mismatch);
+    }
+    table.store(board.hash, depth, nodes);
+    Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn divide_adds_up() {
+        let board =
+            Board::read_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let counts = divide(&board, 3);
+
+        assert_eq!(counts.len(), 48);
+        assert_eq!(counts.iter().map(|&(_, n)| n).sum::<u64>(), 97862);
+
+        let castle = board.parse_move("e1g1", false).unwrap();
+        assert_eq!(counts.iter().find(|&&(m, _)| m == castle).unwrap().1, 2059);
+        assert!(divide(&board, 0).is_empty());
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rkb2bnr/pp2pppp/2p1n3/3p4/q2P4/5NP1/PPP1PP1P/RKBNQBR1 w Aha - 0 9",
+        ] {
+            let board = Board::read_fen(fen).unwrap();
+            let expected = perft(&board, 3);
+
+            for threads in 1..=3 {
+                for split_depth in 0..=3 {
+                    let result = parallel_perft(&board, 3, threads, split_depth);
+                    assert_eq!(result.nodes, expected);
+                    assert_eq!(result.threads.len(), threads);
+
+                    let tasks: usize = result.threads.iter().map(|t| t.tasks).sum();
+                    let split_depth = split_depth.min(3);
+                    assert_eq!(tasks as u64, perft(&board, split_depth));
                 }
             }
         }
     }
 
-perft_test!(
-    perft_startpos: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
-    20,
-    400,
-    8902,
-    197281,
-    4865609,
-    119060324
-);
-
-perft_test!(
-    perft_kiwipete:  "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
-    48,
-    2039,
-    97862,
-    4085603,
-    193690690
-);
-
-perft_test!(
-    perft_pos3: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
-    14,
-    191,
-    2812,
-    43238,
-    674624,
-    11030083,
-    178633661
-);
-
-perft_test!(
-    perft_pos4: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
-    6,
-    264,
-    9467,
-    422333,
-    15833292
-);
-
-perft_test!(
-    perft_pos5: "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
-    44,
-    1486,
-    62379,
-    2103487,
-    89941194
-);
-
-perft_test!(
-    perft_pos6: "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
-    46,
-    2079,
-    89890,
-    3894594,
-    164075551
-);
-
-perft_test!(
-    perft960_position333: "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9";
-    29,
-    502,
-    14569,
-    287739,
-    8652810,
-    191762235
-);
-
-perft_test!(
-    perft960_position404: "rbbqn1kr/pp2p1pp/6n1/2pp1p2/2P4P/P7/BP1PPPP1/R1BQNNKR w HAha - 0 9";
-    27,
-    916,
-    25798,
-    890435,
-    26302461,
-    924181432
-);
-
-perft_test!(
-    perft960_position789: "rqbbknr1/1ppp2pp/p5n1/4pp2/P7/1PP5/1Q1PPPPP/R1BBKNRN w GAga - 0 9";
-    24,
-    600,
-    15347,
-    408207,
-    11029596,
-    308553169
-);
-
-perft_test!(
-    perft960_position726: "rkb2bnr/pp2pppp/2p1n3/3p4/q2P4/5NP1/PPP1PP1P/RKBNQBR1 w Aha - 0 9";
-    29,
-    861,
-    24504,
-    763454,
-    22763215,
-    731511256
-);
-
-#[cfg(feature = "perft-all-960")]
-include!(concat!(env!("OUT_DIR"), "/perft_generated.rs"));
+    #[test]
+    fn hashed_matches_perft() {
+        let mut table = PerftTable::new(4);
+        for (fen, depth, nodes) in [
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                4,
+                4085603,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 5, 674624),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                4,
+                422333,
+            ),
+            (
+                "rkb2bnr/pp2pppp/2p1n3/3p4/q2P4/5NP1/PPP1PP1P/RKBNQBR1 w Aha - 0 9",
+                4,
+                763454,
+            ),
+        ] {
+            let board = Board::read_fen(fen).unwrap();
+            table.clear();
+            assert_eq!(hashed_perft(&board, depth, &mut table), nodes, "{fen}");
+            // Again, now mostly from the table.
+            assert_eq!(hashed_perft(&board, depth, &mut table), nodes, "{fen}");
+
+            table.clear();
+            assert_eq!(
+                verify_hashed_perft(&board, depth - 1, &mut table),
+                Ok(perft(&board, depth - 1))
+            );
+        }
+
+        // A single entry table is overwritten all the time, but must still give the right count.
+        let board = Board::start_pos();
+        assert_eq!(hashed_perft(&board, 4, &mut PerftTable::new(0)), 197281);
+    }
+
+    #[test]
+    fn verify_detects_bad_counts() {
+        let board = Board::start_pos();
+        let mut child = board;
+        child.make_move(board.parse_move("g1f3", false).unwrap());
+
+        // Pretend another position with the same hash was counted before.
+        let mut table = PerftTable::new(1);
+        table.store(child.hash, 2, 1234);
+        let mismatch = verify_hashed_perft(&board, 3, &mut table).unwrap_err();
+        assert_eq!(
+            mismatch,
+            PerftMismatch {
+                fen: child.fen(true),
+                depth: 2,
+                cached: 1234,
+                actual: 440,
+            }
+        );
+    }
+
+    #[test]
+    fn stats_startpos() {
+        let stats = perft_stats(&Board::start_pos(), 5);
+        let expected = [
+            [20, 0, 0, 0, 0, 0, 0, 0, 0],
+            [400, 0, 0, 0, 0, 0, 0, 0, 0],
+            [8902, 34, 0, 0, 0, 12, 0, 0, 0],
+            [197281, 1576, 0, 0, 0, 469, 0, 0, 8],
+            [4865609, 82719, 258, 0, 0, 27351, 6, 0, 347],
+        ];
+        assert_eq!(stats, expected.map(stats_from));
+    }
+
+    #[test]
+    fn stats_kiwipete() {
+        let board =
+            Board::read_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let stats = perft_stats(&board, 4);
+        let expected = [
+            [48, 8, 0, 2, 0, 0, 0, 0, 0],
+            [2039, 351, 1, 91, 0, 3, 0, 0, 0],
+            [97862, 17102, 45, 3162, 0, 993, 0, 0, 1],
+            [4085603, 757163, 1929, 128013, 15172, 25523, 42, 6, 43],
+        ];
+        assert_eq!(stats, expected.map(stats_from));
+    }
+
+    fn stats_from(row: [u64; 9]) -> PerftStats {
+        let [
+            nodes,
+            captures,
+            en_passant,
+            castles,
+            promotions,
+            checks,
+            discovered_checks,
+            double_checks,
+            checkmates,
+        ] = row;
+        PerftStats {
+            nodes,
+            captures,
+            en_passant,
+            castles,
+            promotions,
+            checks,
+            discovered_checks,
+            double_checks,
+            checkmates,
+        }
+    }
+
+    macro_rules! perft_test {
+            ($name:ident: $board:expr; $($nodes:expr),* $(,)?) => {
+                #[test]
+                fn $name() {
+                    const NODES: &'static [u64] = &[$($nodes),*];
+
+                    let board = Board::read_fen($board).unwrap();
+                    for (depth, &nodes) in NODES.iter().enumerate() {
+                        assert_eq!(perft(&board, depth as u8 + 1), nodes);
+                    }
+                }
+            }
+        }
+
+    perft_test!(
+        perft_startpos: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        20,
+        400,
+        8902,
+        197281,
+        4865609,
+        119060324
+    );
+
+    perft_test!(
+        perft_kiwipete:  "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        48,
+        2039,
+        97862,
+        4085603,
+        193690690
+    );
+
+    perft_test!(
+        perft_pos3: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+        14,
+        191,
+        2812,
+        43238,
+        674624,
+        11030083,
+        178633661
+    );
+
+    perft_test!(
+        perft_pos4: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+        6,
+        264,
+        9467,
+        422333,
+        15833292
+    );
+
+    perft_test!(
+        perft_pos5: "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+        44,
+        1486,
+        62379,
+        2103487,
+        89941194
+    );
+
+    perft_test!(
+        perft_pos6: "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+        46,
+        2079,
+        89890,
+        3894594,
+        164075551
+    );
+
+    perft_test!(
+        perft960_position333: "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9";
+        29,
+        502,
+        14569,
+        287739,
+        8652810,
+        191762235
+    );
+
+    perft_test!(
+        perft960_position404: "rbbqn1kr/pp2p1pp/6n1/2pp1p2/2P4P/P7/BP1PPPP1/R1BQNNKR w HAha - 0 9";
+        27,
+        916,
+        25798,
+        890435,
+        26302461,
+        924181432
+    );
+
+    perft_test!(
+        perft960_position789: "rqbbknr1/1ppp2pp/p5n1/4pp2/P7/1PP5/1Q1PPPPP/R1BBKNRN w GAga - 0 9";
+        24,
+        600,
+        15347,
+        408207,
+        11029596,
+        308553169
+    );
+
+    perft_test!(
+        perft960_position726: "rkb2bnr/pp2pppp/2p1n3/3p4/q2P4/5NP1/PPP1PP1P/RKBNQBR1 w Aha - 0 9";
+        29,
+        861,
+        24504,
+        763454,
+        22763215,
+        731511256
+    );
+}
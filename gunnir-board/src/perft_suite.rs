@@ -0,0 +1,339 @@
+//! Running suites of perft positions with known node counts. Two formats are supported, one
+//! position per line:
+//!
+//! - EPD: `<fen> ;D1 20 ;D2 400 ...`. The FEN may leave out the clocks.
+//! - Tab separated: `<name>\t<fen>\t<depth 1 count>\t<depth 2 count>...`, like `perft960.txt`.
+
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::*;
+
+/// A position with its expected node counts. Only `parse` creates cases, so the FEN is always
+/// valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerftCase {
+    name: String,
+    fen: String,
+    expected: Vec<(u8, u64)>,
+}
+
+impl PerftCase {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn fen(&self) -> &str {
+        &self.fen
+    }
+
+    /// The expected node counts by depth, in increasing order of depth.
+    pub fn expected(&self) -> &[(u8, u64)] {
+        &self.expected
+    }
+
+    /// Parses one line of a suite in either format. EPD lines have no name of their own, so
+    /// `default_name` is used for them.
+    pub fn parse(line: &str, default_name: &str) -> Option<Self> {
+        let line = line.trim();
+
+        let (name, fen, expected) = if line.contains(';') {
+            let mut parts = line.split(';');
+            let mut fen = parts.next()?.trim().to_string();
+            if fen.split_ascii_whitespace().count() == 4 {
+                fen.push_str(" 0 1");
+            }
+
+            let mut expected = vec![];
+            for part in parts {
+                let (depth, nodes) = part.trim().split_once(' ')?;
+                let depth = depth.strip_prefix('D')?.parse().ok()?;
+                expected.push((depth, nodes.trim().parse().ok()?));
+            }
+            (default_name.to_string(), fen, expected)
+        } else {
+            let mut parts = line.split('\t').map(str::trim);
+            let name = parts.next()?.to_string();
+            let fen = parts.next()?.to_string();
+            let expected = (1..)
+                .zip(parts)
+                .map(|(depth, nodes)| Some((depth, nodes.parse().ok()?)))
+                .collect::<Option<_>>()?;
+            (name, fen, expected)
+        };
+
+        Board::read_fen(&fen)?;
+        if expected.is_empty() || !expected.is_sorted_by(|a, b| a.0 < b.0) {
+            return None;
+        }
+
+        Some(Self {
+            name,
+            fen,
+            expected,
+        })
+    }
+}
+
+/// Parses a suite, skipping blank lines and lines starting with `#`. Returns the 1-based number
+/// of the first invalid line on failure.
+pub fn parse_perft_suite(text: &str) -> Result<Vec<PerftCase>, usize> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| PerftCase::parse(line, &format!("line {}", i + 1)).ok_or(i + 1))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SuiteLimits {
+    pub max_depth: u8,
+    /// Depths whose expected node count is larger than this are skipped.
+    pub max_nodes: u64,
+    pub threads: usize,
+}
+
+impl Default for SuiteLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: u8::MAX,
+            max_nodes: u64::MAX,
+            threads: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CaseResult {
+    pub name: String,
+    pub fen: String,
+    /// The depths that were run, with their expected and actual node counts.
+    pub depths: Vec<(u8, u64, u64)>,
+    pub time: Duration,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        self.depths
+            .iter()
+            .all(|&(_, expected, actual)| expected == actual)
+    }
+}
+
+fn run_case(case: &PerftCase, limits: &SuiteLimits) -> CaseResult {
+    let start = Instant::now();
+    // `PerftCase::parse` already checked the FEN.
+    let board = Board::read_fen(&case.fen).unwrap();
+
+    let depths = case
+        .expected
+        .iter()
+        .filter(|&&(depth, nodes)| depth <= limits.max_depth && nodes <= limits.max_nodes)
+        .map(|&(depth, expected)| (depth, expected, perft(&board, depth)))
+        .collect();
+
+    CaseResult {
+        name: case.name.clone(),
+        fen: case.fen.clone(),
+        depths,
+        time: start.elapsed(),
+    }
+}
+
+/// Runs every case of a suite within the limits, on `limits.threads` threads. The results are
+/// in the order of the cases.
+pub fn run_perft_suite(cases: &[PerftCase], limits: &SuiteLimits) -> Vec<CaseResult> {
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<_> = thread::scope(|s| {
+        let handles: Vec<_> = (0..limits.threads.max(1))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut results = vec![];
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(case) = cases.get(idx) else {
+                            break results;
+                        };
+                        results.push((idx, run_case(case, limits)));
+                    }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect()
+    });
+
+    results.sort_by_key(|&(idx, _)| idx);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Formats the results as a table with one row per case, followed by all mismatches.
+pub fn format_suite_report(results: &[CaseResult]) -> String {
+    let mut report = String::new();
+    let name_width = results
+        .iter()
+        .map(|r| r.name.len())
+        .max()
+        .unwrap_or(0)
+        .max(8);
+
+    writeln!(
+        report,
+        "{:<name_width$}  {:>5}  {:>12}  {:>8}  Result",
+        "Position", "Depth", "Nodes", "Time"
+    )
+    .unwrap();
+
+    for result in results {
+        let (depth, nodes) = result
+            .depths
+            .last()
+            .map_or((0, 0), |&(depth, _, actual)| (depth, actual));
+        writeln!(
+            report,
+            "{:<name_width$}  {depth:>5}  {nodes:>12}  {:>7.2}s  {}",
+            result.name,
+            result.time.as_secs_f64(),
+            if result.passed() { "pass" } else { "FAIL" }
+        )
+        .unwrap();
+    }
+
+    let failed: Vec<_> = results.iter().filter(|r| !r.passed()).collect();
+    if !failed.is_empty() {
+        writeln!(report, "\nMismatches:").unwrap();
+    }
+    for result in &failed {
+        for &(depth, expected, actual) in &result.depths {
+            if expected != actual {
+                writeln!(
+                    report,
+                    "{} ({}) depth {depth}: expected {expected}, got {actual}",
+                    result.name, result.fen
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    writeln!(
+        report,
+        "\n{}/{} positions passed",
+        results.len() - failed.len(),
+        results.len()
+    )
+    .unwrap();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_formats() {
+        let suite = "\
+# The start position and Kiwipete.
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - ;D1 20 ;D2 400 ;D3 8902
+
+r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ;D1 48 ;D3 97862
+7 \tqbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9 \t22 \t577
+";
+        let cases = parse_perft_suite(suite).unwrap();
+        assert_eq!(
+            cases[0],
+            PerftCase {
+                name: "line 2".to_string(),
+                fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(),
+                expected: vec![(1, 20), (2, 400), (3, 8902)],
+            }
+        );
+        assert_eq!(cases[1].expected, [(1, 48), (3, 97862)]);
+        assert_eq!(cases[2].name, "7");
+        assert_eq!(cases[2].expected, [(1, 22), (2, 577)]);
+
+        assert_eq!(parse_perft_suite("8/8/8/8/8/8/8/8 w - - ;D1 0"), Err(1));
+        assert_eq!(
+            parse_perft_suite("\n4k3/8/8/8/8/8/8/4K3 w - - ;D2 25 ;D1 5"),
+            Err(2)
+        );
+        assert_eq!(
+            parse_perft_suite("1\t4k3/8/8/8/8/8/8/4K3 w - - 0 1\tfive"),
+            Err(1)
+        );
+    }
+
+    #[test]
+    fn reports_mismatches() {
+        let cases = parse_perft_suite(
+            "\
+4k3/8/8/8/8/8/8/4K3 w - - ;D1 5 ;D2 25
+4k3/8/8/8/8/8/8/4K3 b - - ;D1 5 ;D2 24
+",
+        )
+        .unwrap();
+        let limits = SuiteLimits {
+            threads: 2,
+            ..Default::default()
+        };
+        let results = run_perft_suite(&cases, &limits);
+
+        assert!(results[0].passed());
+        assert!(!results[1].passed());
+        assert_eq!(results[1].depths, [(1, 5, 5), (2, 24, 25)]);
+
+        let report = format_suite_report(&results);
+        assert!(
+            report.contains("line 2 (4k3/8/8/8/8/8/8/4K3 b - - 0 1) depth 2: expected 24, got 25")
+        );
+        assert!(report.ends_with("1/2 positions passed\n"));
+
+        let limits = SuiteLimits {
+            max_nodes: 10,
+            ..Default::default()
+        };
+        assert!(run_perft_suite(&cases, &limits)[1].passed());
+    }
+
+    #[test]
+    fn perft960_shallow() {
+        let cases = parse_perft_suite(include_str!("../perft960.txt")).unwrap();
+        assert_eq!(cases.len(), 960);
+
+        let limits = SuiteLimits {
+            max_nodes: 20_000,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            ..Default::default()
+        };
+        let results = run_perft_suite(&cases, &limits);
+        assert!(results.iter().all(|r| r.depths.len() >= 2));
+        assert!(
+            results.iter().all(CaseResult::passed),
+            "{}",
+            format_suite_report(&results)
+        );
+    }
+
+    #[cfg(feature = "perft-all-960")]
+    #[test]
+    fn perft960_full() {
+        let cases = parse_perft_suite(include_str!("../perft960.txt")).unwrap();
+        let limits = SuiteLimits {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            ..Default::default()
+        };
+        let results = run_perft_suite(&cases, &limits);
+        assert!(
+            results.iter().all(CaseResult::passed),
+            "{}",
+            format_suite_report(&results)
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! A deliberately simple legal move generator, used to test `Board::gen_moves` against. It
+//! only looks at a mailbox of the position: pseudo-legal moves are found by walking rays square
+//! by square, and a move is legal if our king isn't attacked after making it. Perft counts can
+//! hide bugs that cancel out, which comparing the generated moves directly doesn't.
+
+use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+use crate::*;
+
+type Mailbox = [Option<(Color, PieceType)>; 64];
+
+const KNIGHT_STEPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_STEPS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+const ORTHOGONAL: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const DIAGONAL: [(i8, i8); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+const PROMOTIONS: [PieceType; 4] = [
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+];
+
+fn mailbox(board: &Board) -> Mailbox {
+    let mut mailbox = [None; 64];
+    for &sq in Square::ALL {
+        for color in [Color::White, Color::Black] {
+            if let Some(pt) = board.colored_piece_on(sq, color) {
+                mailbox[sq.idx() as usize] = Some((color, pt));
+            }
+        }
+    }
+    mailbox
+}
+
+fn at(mailbox: &Mailbox, sq: Square) -> Option<(Color, PieceType)> {
+    mailbox[sq.idx() as usize]
+}
+
+/// The squares reached by walking from `from` in the direction `(df, dr)`, up to and including
+/// the first occupied square.
+fn ray(mailbox: &Mailbox, from: Square, (df, dr): (i8, i8)) -> Vec<Square> {
+    let mut squares = vec![];
+    let mut sq = from;
+    while let Some(next) = sq.try_offset(df, dr) {
+        squares.push(next);
+        if at(mailbox, next).is_some() {
+            break;
+        }
+        sq = next;
+    }
+    squares
+}
+
+fn pawn_direction(color: Color) -> i8 {
+    if color == Color::White { 1 } else { -1 }
+}
+
+/// Whether a piece of color `by` attacks `sq`.
+fn attacked(mailbox: &Mailbox, sq: Square, by: Color) -> bool {
+    let is = |sq: Option<Square>, pts: &[PieceType]| {
+        sq.and_then(|sq| at(mailbox, sq))
+            .is_some_and(|(color, pt)| color == by && pts.contains(&pt))
+    };
+
+    let pawn_rank = -pawn_direction(by);
+    is(sq.try_offset(-1, pawn_rank), &[PieceType::Pawn])
+        || is(sq.try_offset(1, pawn_rank), &[PieceType::Pawn])
+        || KNIGHT_STEPS
+            .iter()
+            .any(|&(df, dr)| is(sq.try_offset(df, dr), &[PieceType::Knight]))
+        || KING_STEPS
+            .iter()
+            .any(|&(df, dr)| is(sq.try_offset(df, dr), &[PieceType::King]))
+        || ORTHOGONAL.iter().any(|&dir| {
+            is(
+                ray(mailbox, sq, dir).last().copied(),
+                &[PieceType::Rook, PieceType::Queen],
+            )
+        })
+        || DIAGONAL.iter().any(|&dir| {
+            is(
+                ray(mailbox, sq, dir).last().copied(),
+                &[PieceType::Bishop, PieceType::Queen],
+            )
+        })
+}
+
+fn pawn_moves(board: &Board, mailbox: &Mailbox, from: Square, moves: &mut Vec<Move>) {
+    let us = board.stm;
+    let dir = pawn_direction(us);
+    let mut push = |to: Square| {
+        if to.rank() == Rank::R8.relative_to(us) {
+            moves.extend(PROMOTIONS.map(|pt| Move::new_promotion(from, to, pt)));
+        } else {
+            moves.push(Move::new(from, to, MoveFlag::None));
+        }
+    };
+
+    if let Some(to) = from.try_offset(0, dir)
+        && at(mailbox, to).is_none()
+    {
+        push(to);
+        if from.rank() == Rank::R2.relative_to(us)
+            && let Some(to) = to.try_offset(0, dir)
+            && at(mailbox, to).is_none()
+        {
+            push(to);
+        }
+    }
+
+    for df in [-1, 1] {
+        let Some(to) = from.try_offset(df, dir) else {
+            continue;
+        };
+        if at(mailbox, to).is_some_and(|(color, _)| color != us) {
+            push(to);
+        }
+    }
+
+    if from.rank() == Rank::R5.relative_to(us)
+        && let Some(file) = board.en_passant
+        && from.file().idx().abs_diff(file.idx()) == 1
+    {
+        let to = Square::from_file_rank(file, from.rank().offset(dir));
+        moves.push(Move::new(from, to, MoveFlag::EnPassant));
+    }
+}
+
+fn castling_moves(board: &Board, mailbox: &Mailbox, moves: &mut Vec<Move>) {
+    let us = board.stm;
+    let rights = board.castles[us];
+    let king = board.king(us);
+    let rank = Rank::R1.relative_to(us);
+
+    for (rook_file, king_to, rook_to) in [
+        (rights.short, File::G, File::F),
+        (rights.long, File::C, File::D),
+    ] {
+        let Some(rook_file) = rook_file else {
+            continue;
+        };
+        let rook = Square::from_file_rank(rook_file, rank);
+
+        // Everything between the outermost of the four squares must be empty, except for the
+        // king and rook themselves.
+        let files = [king.file(), rook_file, king_to, rook_to];
+        let (min, max) = (files.iter().min().unwrap(), files.iter().max().unwrap());
+        let blocked = File::ALL[min.idx() as usize..=max.idx() as usize]
+            .iter()
+            .map(|&file| Square::from_file_rank(file, rank))
+            .any(|sq| sq != king && sq != rook && at(mailbox, sq).is_some());
+
+        // The king may not castle out of, through or into check.
+        let (from, to) = (king.file().min(king_to), king.file().max(king_to));
+        let through_check = File::ALL[from.idx() as usize..=to.idx() as usize]
+            .iter()
+            .any(|&file| attacked(mailbox, Square::from_file_rank(file, rank), !us));
+
+        if !blocked && !through_check {
+            let to = Square::from_file_rank(king_to, rank);
+            moves.push(Move::new(king, to, MoveFlag::Castle));
+        }
+    }
+}
+
+/// The mailbox after making `mov`, which may leave our king in check.
+fn after_move(board: &Board, mailbox: &Mailbox, mov: Move) -> Mailbox {
+    let us = board.stm;
+    let (from, to) = (mov.from(), mov.to());
+    let mut mailbox = *mailbox;
+    let piece = mailbox[from.idx() as usize].take();
+
+    match mov.move_flag() {
+        MoveFlag::Castle => {
+            let rights = board.castles[us];
+            let (rook_file, rook_to) = if to.file() == File::G {
+                (rights.short.unwrap(), File::F)
+            } else {
+                (rights.long.unwrap(), File::D)
+            };
+            mailbox[Square::from_file_rank(rook_file, from.rank()).idx() as usize] = None;
+            mailbox[Square::from_file_rank(rook_to, from.rank()).idx() as usize] =
+                Some((us, PieceType::Rook));
+        }
+        MoveFlag::EnPassant => {
+            mailbox[Square::from_file_rank(to.file(), from.rank()).idx() as usize] = None;
+        }
+        MoveFlag::None | MoveFlag::Promotion => {}
+    }
+
+    mailbox[to.idx() as usize] = mov.promotes_to().map(|pt| (us, pt)).or(piece);
+    mailbox
+}
+
+/// Generates all legal moves of the side to move.
+pub fn reference_moves(board: &Board) -> Vec<Move> {
+    let us = board.stm;
+    let mailbox = mailbox(board);
+    let mut moves = vec![];
+
+    for &from in Square::ALL {
+        let Some((color, pt)) = at(&mailbox, from) else {
+            continue;
+        };
+        if color != us {
+            continue;
+        }
+
+        let targets: Vec<Square> = match pt {
+            PieceType::Pawn => {
+                pawn_moves(board, &mailbox, from, &mut moves);
+                continue;
+            }
+            PieceType::Knight => KNIGHT_STEPS
+                .iter()
+                .filter_map(|&(df, dr)| from.try_offset(df, dr))
+                .collect(),
+            PieceType::King => KING_STEPS
+                .iter()
+                .filter_map(|&(df, dr)| from.try_offset(df, dr))
+                .collect(),
+            PieceType::Bishop => DIAGONAL
+                .iter()
+                .flat_map(|&d| ray(&mailbox, from, d))
+                .collect(),
+            PieceType::Rook => ORTHOGONAL
+                .iter()
+                .flat_map(|&d| ray(&mailbox, from, d))
+                .collect(),
+            PieceType::Queen => DIAGONAL
+                .iter()
+                .chain(&ORTHOGONAL)
+                .flat_map(|&d| ray(&mailbox, from, d))
+                .collect(),
+        };
+
+        for to in targets {
+            if at(&mailbox, to).is_none_or(|(color, _)| color != us) {
+                moves.push(Move::new(from, to, MoveFlag::None));
+            }
+        }
+    }
+    castling_moves(board, &mailbox, &mut moves);
+
+    // Making the moves on the board would trip `debug-invariants` for the illegal ones, so they
+    // are made on the mailbox instead.
+    moves.retain(|&mov| {
+        let mailbox = after_move(board, &mailbox, mov);
+        let king = Square::ALL
+            .iter()
+            .find(|&&sq| at(&mailbox, sq) == Some((us, PieceType::King)))
+            .unwrap();
+        !attacked(&mailbox, *king, !us)
+    });
+    moves
+}
+
+/// Compares `gen_moves` with `reference_moves` in the positions of random playouts from every
+/// position of `perft960.txt`, and panics with the first difference.
+pub fn differential_test(seed: u64, playouts_per_position: usize, plies: usize) {
+    let mut rng = SmallRng::seed_from_u64(seed);
+
+    for line in include_str!("../perft960.txt").lines() {
+        let start = Board::read_fen(line.split('\t').nth(1).unwrap().trim()).unwrap();
+
+        for _ in 0..playouts_per_position {
+            let mut board = start;
+            for _ in 0..plies {
+                let mut moves = vec![];
+                board.gen_moves(|m| moves.extend(m));
+                let reference = reference_moves(&board);
+
+                let uci = |moves: &[Move], other: &[Move]| {
+                    let mut uci: Vec<_> = moves
+                        .iter()
+                        .filter(|m| !other.contains(m))
+                        .map(|&m| board.move_to_uci(m, true))
+                        .collect();
+                    uci.sort();
+                    uci
+                };
+                let (missing, extra) = (uci(&reference, &moves), uci(&moves, &reference));
+                assert!(
+                    missing.is_empty() && extra.is_empty() && moves.len() == reference.len(),
+                    "gen_moves differs from the reference in {}\nmissing: {missing:?}\nextra: {extra:?}",
+                    board.fen(true)
+                );
+
+                if moves.is_empty() || board.halfmove_clock >= 99 {
+                    break;
+                }
+                board.make_move(moves[rng.random_range(0..moves.len())]);
+            }
+        }
+    }
+}
+
+#[test]
+fn reference_perft() {
+    fn perft(board: &Board, depth: u8) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        reference_moves(board)
+            .into_iter()
+            .map(|mov| {
+                let mut child = *board;
+                child.make_move(mov);
+                perft(&child, depth - 1)
+            })
+            .sum()
+    }
+
+    // Compensating errors in both generators are unlikely, so this tests the reference itself.
+    for (fen, nodes) in [
+        (
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            97862,
+        ),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 2812),
+        (
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            9467,
+        ),
+        (
+            "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9",
+            14569,
+        ),
+    ] {
+        assert_eq!(perft(&Board::read_fen(fen).unwrap(), 3), nodes, "{fen}");
+    }
+}
+
+#[test]
+fn differential() {
+    differential_test(0x5eed, 1, 40);
+}
+
+/// Millions of positions. Run with `cargo test --release -- --ignored differential_long`.
+#[test]
+#[ignore]
+fn differential_long() {
+    differential_test(0xd1ff, 20, 200);
+}
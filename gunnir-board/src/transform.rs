@@ -0,0 +1,132 @@
+use crate::*;
+
+impl Board {
+    /// Builds a board with every piece of `self` moved to `map(sq)` and given the color
+    /// `recolor(color)`. Castling rights, the side to move and en passant are left to the caller.
+    fn remap(&self, map: impl Fn(Square) -> Square, recolor: impl Fn(Color) -> Color) -> Self {
+        let mut board = Board::empty();
+        for color in Color::ALL.iter().copied() {
+            for sq in self.occupied[color] {
+                let pt = self.mailbox[sq].unwrap();
+                let sq = map(sq);
+                board.toggle_square(sq, recolor(color), pt);
+                board.mailbox[sq] = Some(pt);
+            }
+        }
+
+        board.halfmove_clock = self.halfmove_clock;
+        board.fullmove_count = self.fullmove_count;
+        board
+    }
+
+    /// Mirrors the board vertically and swaps the colors of all pieces, the side to move and the
+    /// castling rights. The result is the same position from the other side's point of view, so
+    /// it has the same perft counts and, for a symmetric evaluation, the same evaluation.
+    pub fn flip_colors(&self) -> Self {
+        let flip =
+            |sq: Square| Square::from_file_rank(sq.file(), sq.rank().relative_to(Color::Black));
+        let mut board = self.remap(flip, |color| !color);
+
+        board.stm = !self.stm;
+        if board.stm == Color::Black {
+            board.hash ^= ZOBRIST.black_to_move;
+        }
+        for color in Color::ALL.iter().copied() {
+            let rights = self.castles[color];
+            board.set_castles(!color, rights.short, true);
+            board.set_castles(!color, rights.long, false);
+        }
+        board.set_en_passant(self.en_passant);
+
+        board.calc_pinned_and_checkers();
+        board
+    }
+
+    /// Mirrors the board horizontally, so pieces on the A file end up on the H file. Castling
+    /// doesn't work the same way on the mirrored board, so this returns `None` if either side
+    /// may still castle.
+    pub fn mirror_horizontal(&self) -> Option<Self> {
+        if Color::ALL
+            .iter()
+            .any(|&c| self.castles[c].short.is_some() || self.castles[c].long.is_some())
+        {
+            return None;
+        }
+
+        let mirror = |file: File| File::from_idx(7 - file.idx());
+        let mut board = self.remap(
+            |sq| Square::from_file_rank(mirror(sq.file()), sq.rank()),
+            |color| color,
+        );
+
+        board.stm = self.stm;
+        if board.stm == Color::Black {
+            board.hash ^= ZOBRIST.black_to_move;
+        }
+        board.set_en_passant(self.en_passant.map(mirror));
+
+        board.calc_pinned_and_checkers();
+        Some(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+    #[test]
+    fn flip_and_mirror() {
+        let board = Board::read_fen("r3k2r/8/8/8/3pP3/8/8/R3K1R1 b Qk e3 5 40").unwrap();
+        let flipped = board.flip_colors();
+        assert_eq!(
+            flipped.fen(false),
+            "r3k1r1/8/8/3Pp3/8/8/8/R3K2R w Kq e6 5 40"
+        );
+        assert_eq!(
+            flipped.fen(true),
+            "r3k1r1/8/8/3Pp3/8/8/8/R3K2R w Ha e6 5 40"
+        );
+        assert_eq!(flipped.checkers, Bitboard::EMPTY);
+        assert_eq!(board.mirror_horizontal().map(|b| b.hash), None);
+
+        let board = Board::read_fen("8/1k6/8/8/2pP4/8/1K3Q2/8 b - d3 0 1").unwrap();
+        let mirrored = board.mirror_horizontal().unwrap();
+        assert_eq!(mirrored.fen(false), "8/6k1/8/8/4Pp2/8/2Q3K1/8 b - e3 0 1");
+
+        let board = Board::read_fen("8/1k6/8/8/8/8/1K6/7Q b - - 0 1").unwrap();
+        let mirrored = board.mirror_horizontal().unwrap();
+        assert_eq!(mirrored.fen(false), "8/6k1/8/8/8/8/6K1/Q7 b - - 0 1");
+        assert_eq!(mirrored.checkers, Square::A1.bitboard());
+    }
+
+    #[test]
+    fn transforms_preserve_perft() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+
+        for line in include_str!("../perft960.txt").lines().step_by(16) {
+            let mut board = Board::read_fen(line.split('\t').nth(1).unwrap().trim()).unwrap();
+
+            for _ in 0..40 {
+                let flipped = board.flip_colors();
+                assert_eq!(flipped.check_invariants(), Ok(()), "{}", board.fen(true));
+                assert_eq!(perft(&flipped, 2), perft(&board, 2), "{}", board.fen(true));
+                assert_eq!(flipped.flip_colors().fen(true), board.fen(true));
+                assert_eq!(flipped.flip_colors().hash, board.hash);
+
+                if let Some(mirrored) = board.mirror_horizontal() {
+                    assert_eq!(mirrored.check_invariants(), Ok(()), "{}", board.fen(true));
+                    assert_eq!(perft(&mirrored, 2), perft(&board, 2), "{}", board.fen(true));
+                    assert_eq!(mirrored.mirror_horizontal().unwrap().hash, board.hash);
+                }
+
+                let mut moves = vec![];
+                board.gen_moves(|m| moves.extend(m));
+                if moves.is_empty() || board.halfmove_clock >= 99 {
+                    break;
+                }
+                board.make_move(moves[rng.random_range(0..moves.len())]);
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Support for tuning the evaluation weights. Every weight in `params` belongs to a
+//! `ParamGroup`, and the evaluation looks all of them up through `Tracer::weight`. Since the
+//! evaluation is linear in the weights, recording how often each one is used is enough to
+//! evaluate a position with any other set of weights.
+
+use crate::*;
+
+define_enum!(
+    /// Every group of evaluation weights, in the order they appear in the tuner's parameter
+    /// vector.
+    #[derive(Debug)]
+    pub enum ParamGroup {
+        Material,
+        Pst,
+        Passed,
+        Connected,
+        Isolated,
+        Doubled,
+        Backward,
+        PasserOwnKingDistance,
+        PasserTheirKingDistance,
+        KnightMobility,
+        BishopMobility,
+        RookMobility,
+        QueenMobility,
+        KingAttack,
+        Shelter,
+        Storm,
+        KingOpenFile,
+        KingSemiOpenFile,
+    }
+);
+
+impl ParamGroup {
+    /// The current weights of the group. Multi-dimensional tables are flattened.
+    #[inline(always)]
+    pub const fn values(self) -> &'static [Score] {
+        match self {
+            Self::Material => &MATERIAL,
+            Self::Pst => PST.as_flattened(),
+            Self::Passed => &PASSED,
+            Self::Connected => &CONNECTED,
+            Self::Isolated => std::slice::from_ref(&ISOLATED),
+            Self::Doubled => std::slice::from_ref(&DOUBLED),
+            Self::Backward => std::slice::from_ref(&BACKWARD),
+            Self::PasserOwnKingDistance => &PASSER_OWN_KING_DISTANCE,
+            Self::PasserTheirKingDistance => &PASSER_THEIR_KING_DISTANCE,
+            Self::KnightMobility => &KNIGHT_MOBILITY,
+            Self::BishopMobility => &BISHOP_MOBILITY,
+            Self::RookMobility => &ROOK_MOBILITY,
+            Self::QueenMobility => &QUEEN_MOBILITY,
+            Self::KingAttack => &KING_ATTACK,
+            Self::Shelter => &SHELTER,
+            Self::Storm => &STORM,
+            Self::KingOpenFile => std::slice::from_ref(&KING_OPEN_FILE),
+            Self::KingSemiOpenFile => std::slice::from_ref(&KING_SEMI_OPEN_FILE),
+        }
+    }
+
+    /// The name of the constant in `params`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Material => "MATERIAL",
+            Self::Pst => "PST",
+            Self::Passed => "PASSED",
+            Self::Connected => "CONNECTED",
+            Self::Isolated => "ISOLATED",
+            Self::Doubled => "DOUBLED",
+            Self::Backward => "BACKWARD",
+            Self::PasserOwnKingDistance => "PASSER_OWN_KING_DISTANCE",
+            Self::PasserTheirKingDistance => "PASSER_THEIR_KING_DISTANCE",
+            Self::KnightMobility => "KNIGHT_MOBILITY",
+            Self::BishopMobility => "BISHOP_MOBILITY",
+            Self::RookMobility => "ROOK_MOBILITY",
+            Self::QueenMobility => "QUEEN_MOBILITY",
+            Self::KingAttack => "KING_ATTACK",
+            Self::Shelter => "SHELTER",
+            Self::Storm => "STORM",
+            Self::KingOpenFile => "KING_OPEN_FILE",
+            Self::KingSemiOpenFile => "KING_SEMI_OPEN_FILE",
+        }
+    }
+
+    /// The lengths of the constant's (nested) array dimensions, empty for a single `Score`.
+    pub fn dims(self) -> Vec<usize> {
+        match self {
+            Self::Isolated | Self::Doubled | Self::Backward => vec![],
+            Self::KingOpenFile | Self::KingSemiOpenFile => vec![],
+            Self::Pst => vec![PieceType::COUNT, Square::COUNT],
+            _ => vec![self.size()],
+        }
+    }
+
+    /// The number of weights in the group.
+    #[inline]
+    pub const fn size(self) -> usize {
+        self.values().len()
+    }
+
+    /// Where the group starts in the parameter vector.
+    pub const fn offset(self) -> usize {
+        let mut offset = 0;
+        let mut i = 0;
+        while i < self.idx() {
+            offset += Self::from_idx(i).size();
+            i += 1;
+        }
+        offset
+    }
+}
+
+/// The total number of evaluation weights.
+pub const PARAM_COUNT: usize = {
+    let last = ParamGroup::ALL[ParamGroup::COUNT - 1];
+    last.offset() + last.size()
+};
+
+/// All current weights, in parameter vector order.
+pub fn param_values() -> Vec<Score> {
+    ParamGroup::ALL
+        .iter()
+        .flat_map(|g| g.values().iter().copied())
+        .collect()
+}
+
+/// Records how often each weight contributes to the evaluation, from white's point of view.
+/// Uses by black count negatively, so a weight used equally by both colors cancels out.
+#[derive(Clone)]
+pub struct Coefficients {
+    pub counts: Vec<i32>,
+}
+
+impl Tracer for Coefficients {
+    const ENABLED: bool = true;
+
+    #[inline]
+    fn trace(&mut self, _term: Term, _color: Color, _score: Score) {}
+
+    #[inline]
+    fn weight(&mut self, group: ParamGroup, idx: usize, color: Color, count: i32) -> Score {
+        self.counts[group.offset() + idx] += match color {
+            Color::White => count,
+            Color::Black => -count,
+        };
+        group.values()[idx] * count
+    }
+}
+
+impl Coefficients {
+    /// Computes the coefficients of every weight for `board`, as sparse `(index, count)` pairs.
+    pub fn of(board: &Board) -> Vec<(usize, i32)> {
+        let mut coeffs = Self {
+            counts: vec![0; PARAM_COUNT],
+        };
+        let pawns = PawnEntry::compute_traced(board, &mut coeffs);
+        evaluate_with(board, &pawns, &mut coeffs);
+
+        coeffs
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &c)| c != 0)
+            .map(|(i, &c)| (i, c))
+            .collect()
+    }
+}
+
+/// Evaluates a position from white's point of view given its coefficients, the game phase and
+/// a set of weights. With the weights from `param_values`, this matches `evaluate`.
+pub fn linear_eval(coeffs: &[(usize, i32)], phase: i32, weights: &[Score]) -> i32 {
+    let sum: Score = coeffs.iter().map(|&(i, c)| weights[i] * c).sum();
+    sum.taper(phase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+    #[test]
+    fn layout() {
+        assert_eq!(ParamGroup::Material.offset(), 0);
+        assert_eq!(ParamGroup::Pst.offset(), PieceType::COUNT);
+        assert_eq!(ParamGroup::Passed.offset(), PieceType::COUNT * 65);
+        assert_eq!(param_values().len(), PARAM_COUNT);
+    }
+
+    #[test]
+    fn coefficients_reproduce_evaluation() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+        let weights = param_values();
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9",
+        ] {
+            let mut board = Board::read_fen(fen).unwrap();
+
+            for _ in 0..50 {
+                let white = match board.stm {
+                    Color::White => evaluate(&board),
+                    Color::Black => -evaluate(&board),
+                };
+                let coeffs = Coefficients::of(&board);
+                assert_eq!(linear_eval(&coeffs, board.phase(), &weights), white);
+
+                let mut moves = vec![];
+                board.gen_moves(|m| moves.extend(m));
+                if moves.is_empty() || board.halfmove_clock >= 99 {
+                    break;
+                }
+                board.make_move(moves[rng.random_range(0..moves.len())]);
+            }
+        }
+    }
+}
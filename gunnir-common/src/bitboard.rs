@@ -82,6 +82,33 @@ impl Bitboard {
         shift_bb::<D>(self, steps)
     }
 
+    /// Smears every set bit towards rank 8, i.e. sets all squares in front of a set square,
+    /// as seen from white.
+    #[inline]
+    pub const fn fill_up(self) -> Self {
+        let mut bb = self.0;
+        bb |= bb << 8;
+        bb |= bb << 16;
+        bb |= bb << 32;
+        Self(bb)
+    }
+
+    /// Smears every set bit towards rank 1.
+    #[inline]
+    pub const fn fill_down(self) -> Self {
+        let mut bb = self.0;
+        bb |= bb >> 8;
+        bb |= bb >> 16;
+        bb |= bb >> 32;
+        Self(bb)
+    }
+
+    /// Sets every square on a file that contains at least one set square.
+    #[inline]
+    pub const fn file_fill(self) -> Self {
+        self.fill_up().union(self.fill_down())
+    }
+
     #[inline]
     pub const fn main_diag_for(sq: Square) -> Self {
         let shift = sq.rank().idx() as i8 - sq.file().idx() as i8;
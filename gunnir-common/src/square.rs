@@ -141,6 +141,14 @@ impl Square {
         self.try_offset(df, dr).expect("Invalid square offset")
     }
 
+    /// The number of king moves it takes to get from one square to the other.
+    #[inline]
+    pub const fn distance(self, other: Self) -> u8 {
+        let df = self.file().idx().abs_diff(other.file().idx());
+        let dr = self.rank().idx().abs_diff(other.rank().idx());
+        if df > dr { df } else { dr }
+    }
+
     #[inline]
     pub fn parse(s: &str) -> Option<Self> {
         let &[f, r]: &[u8; 2] = s.as_bytes().try_into().ok()?;
@@ -0,0 +1,149 @@
+use std::io::{self, Write};
+
+use crate::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    BlackWin,
+    Draw,
+    WhiteWin,
+}
+
+impl GameResult {
+    /// The result from white's point of view: 0 for a loss, 0.5 for a draw and 1 for a win.
+    #[inline]
+    pub fn value(self) -> f32 {
+        self as u8 as f32 / 2.0
+    }
+
+    /// A win for `color`.
+    #[inline]
+    pub fn win_for(color: Color) -> Self {
+        match color {
+            Color::White => Self::WhiteWin,
+            Color::Black => Self::BlackWin,
+        }
+    }
+}
+
+/// A position from a self-play game, along with its search score and the game's result.
+#[derive(Clone, Copy)]
+pub struct Record {
+    pub board: Board,
+    /// The search score from white's point of view, in centipawns.
+    pub score: i16,
+    pub result: GameResult,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputFormat {
+    /// One `<fen> | <score> | <result>` line per record, with the result as 1.0, 0.5 or 0.0.
+    /// This is one of the formats `gunnir tune` reads.
+    Text,
+    /// Fixed size records of `BINARY_RECORD_LEN` bytes, see `Record::to_bytes`.
+    Binary,
+}
+
+pub const BINARY_RECORD_LEN: usize = 35;
+
+impl Record {
+    /// Writes the record in the given format. Castling rights are written in Shredder notation
+    /// if `chess960` is set.
+    pub fn write(
+        &self,
+        out: &mut impl Write,
+        format: OutputFormat,
+        chess960: bool,
+    ) -> io::Result<()> {
+        match format {
+            OutputFormat::Text => writeln!(
+                out,
+                "{} | {} | {:.1}",
+                self.board.fen(chess960),
+                self.score,
+                self.result.value()
+            ),
+            OutputFormat::Binary => out.write_all(&self.to_bytes()),
+        }
+    }
+
+    /// Encodes the record as the packed board (see `Board::pack`), followed by the score in two
+    /// bytes, little endian, and the result in one (0 for a black win, 1 for a draw, 2 for a
+    /// white win).
+    pub fn to_bytes(&self) -> [u8; BINARY_RECORD_LEN] {
+        let mut bytes = [0; BINARY_RECORD_LEN];
+        bytes[0..32].copy_from_slice(&self.board.pack());
+        bytes[32..34].copy_from_slice(&self.score.to_le_bytes());
+        bytes[34] = self.result as u8;
+        bytes
+    }
+
+    /// Decodes a record written by `to_bytes`, or returns `None` if it isn't valid.
+    pub fn from_bytes(bytes: &[u8; BINARY_RECORD_LEN]) -> Option<Self> {
+        Some(Self {
+            board: Board::unpack(bytes[0..32].try_into().unwrap())?,
+            score: i16::from_le_bytes([bytes[32], bytes[33]]),
+            result: match bytes[34] {
+                0 => GameResult::BlackWin,
+                1 => GameResult::Draw,
+                2 => GameResult::WhiteWin,
+                _ => return None,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_format() {
+        let record = Record {
+            board: Board::start_pos(),
+            score: -25,
+            result: GameResult::Draw,
+        };
+        let mut out = vec![];
+        record.write(&mut out, OutputFormat::Text, false).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 | -25 | 0.5\n"
+        );
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 13 47",
+            "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9",
+        ] {
+            let record = Record {
+                board: Board::read_fen(fen).unwrap(),
+                score: -1234,
+                result: GameResult::WhiteWin,
+            };
+            let mut bytes = vec![];
+            record
+                .write(&mut bytes, OutputFormat::Binary, false)
+                .unwrap();
+            assert_eq!(bytes.len(), BINARY_RECORD_LEN);
+
+            let decoded = Record::from_bytes(bytes.as_slice().try_into().unwrap()).unwrap();
+            assert_eq!(decoded.board.fen(true), record.board.fen(true));
+            assert_eq!(decoded.score, -1234);
+            assert_eq!(decoded.result, GameResult::WhiteWin);
+        }
+
+        let mut bad_result = Record {
+            board: Board::start_pos(),
+            score: 0,
+            result: GameResult::Draw,
+        }
+        .to_bytes();
+        bad_result[34] = 3;
+        assert!(Record::from_bytes(&bad_result).is_none());
+    }
+}
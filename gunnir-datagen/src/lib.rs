@@ -0,0 +1,11 @@
+pub use gunnir_search::*;
+
+pub mod format;
+pub mod openings;
+pub mod positions;
+pub mod selfplay;
+
+pub use format::*;
+pub use openings::*;
+pub use positions::*;
+pub use selfplay::*;
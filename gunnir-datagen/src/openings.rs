@@ -0,0 +1,53 @@
+use rand::Rng;
+
+use crate::*;
+
+/// A uniformly random Chess960 start position.
+pub fn random_chess960(rng: &mut impl Rng) -> Board {
+    Board::chess960(rng.random_range(0..960)).unwrap()
+}
+
+/// Plays `plies` uniformly random moves from the classical start position, or from a random
+/// Chess960 position if `chess960` is set. Returns `None` if the game ended on the way.
+pub fn random_opening(rng: &mut impl Rng, plies: usize, chess960: bool) -> Option<Board> {
+    let start = if chess960 {
+        random_chess960(rng)
+    } else {
+        Board::start_pos()
+    };
+
+    random_playout(rng, &start, plies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::SmallRng};
+    use std::collections::HashSet;
+
+    #[test]
+    fn chess960_positions() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+        let mut seen = HashSet::new();
+
+        for _ in 0..20_000 {
+            seen.insert(random_chess960(&mut rng).chess960_index().unwrap());
+        }
+
+        // 20000 uniform draws all but certainly cover every one of the 960 positions.
+        assert_eq!(seen.len(), 960);
+    }
+
+    #[test]
+    fn openings_are_playable() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+        for chess960 in [false, true] {
+            for _ in 0..100 {
+                if let Some(board) = random_opening(&mut rng, 8, chess960) {
+                    assert_eq!(board.fullmove_count, 5);
+                    assert_eq!(board.stm, Color::White);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use enum_map::EnumMap;
+use rand::Rng;
+
+use crate::*;
+
+/// The pieces on the board, like `KRPvKR` for king, rook and pawn against king and rook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaterialSignature {
+    pub pieces: EnumMap<Color, Vec<PieceType>>,
+}
+
+impl MaterialSignature {
+    /// Parses a signature with white's pieces before the `v` and black's after it. Both sides
+    /// need exactly one king.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (white, black) = s.split_once(['v', 'V'])?;
+        let mut pieces = EnumMap::default();
+
+        for (color, side) in [(Color::White, white), (Color::Black, black)] {
+            let side: Vec<_> = side
+                .chars()
+                .map(|ch| PieceType::from_char(ch).filter(|_| ch.is_ascii_uppercase()))
+                .collect::<Option<_>>()?;
+            if side.iter().filter(|&&pt| pt == PieceType::King).count() != 1 {
+                return None;
+            }
+            pieces[color] = side;
+        }
+
+        // Anything more can't be placed, and isn't reachable in a game anyway.
+        if pieces.values().any(|side: &Vec<_>| side.len() > 16) {
+            return None;
+        }
+
+        Some(Self { pieces })
+    }
+}
+
+/// How often `random_position` tries to place the pieces before giving up.
+const MAX_ATTEMPTS: usize = 10_000;
+
+/// Places the pieces of `signature` on uniformly random squares, with a random side to move,
+/// until the result is a legal position. Pawns don't go on the first or last rank, and the side
+/// not to move may not be in check. Returns `None` if no legal position was found, which
+/// usually means there is none.
+pub fn random_position(rng: &mut impl Rng, signature: &MaterialSignature) -> Option<Board> {
+    let pawn_squares = Square::ALL
+        .iter()
+        .copied()
+        .filter(|sq| !matches!(sq.rank(), Rank::R1 | Rank::R8));
+
+    for _ in 0..MAX_ATTEMPTS {
+        let mut mailbox: [Option<char>; 64] = [None; 64];
+        let mut placed = true;
+
+        for (color, pieces) in &signature.pieces {
+            for &pt in pieces {
+                let empty: Vec<_> = if pt == PieceType::Pawn {
+                    pawn_squares.clone().collect()
+                } else {
+                    Square::ALL.to_vec()
+                };
+                let empty: Vec<_> = empty
+                    .into_iter()
+                    .filter(|sq| mailbox[sq.idx() as usize].is_none())
+                    .collect();
+                if empty.is_empty() {
+                    placed = false;
+                    break;
+                }
+
+                let sq = empty[rng.random_range(0..empty.len())];
+                mailbox[sq.idx() as usize] = Some(pt.to_char(color));
+            }
+        }
+        if !placed {
+            continue;
+        }
+
+        let mut fen = String::new();
+        for rank in Rank::ALL.iter().rev() {
+            let mut empty = 0;
+            for &file in File::ALL {
+                match mailbox[Square::from_file_rank(file, *rank).idx() as usize] {
+                    Some(ch) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(ch);
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if *rank != Rank::R1 {
+                fen.push('/');
+            }
+        }
+
+        let stm = if rng.random() { 'w' } else { 'b' };
+        if let Some(board) = Board::read_fen(&format!("{fen} {stm} - - 0 1")) {
+            return Some(board);
+        }
+    }
+
+    None
+}
+
+/// Plays `plies` uniformly random moves from `start`. Returns `None` if the game ended on the
+/// way, or in the final position. Reaching the fifty-move rule also counts as the game ending.
+pub fn random_playout(rng: &mut impl Rng, start: &Board, plies: usize) -> Option<Board> {
+    let mut board = *start;
+
+    for _ in 0..plies {
+        let mut moves = vec![];
+        board.gen_moves(|m| moves.extend(m));
+        if moves.is_empty() || board.halfmove_clock >= 99 {
+            return None;
+        }
+        board.make_move(moves[rng.random_range(0..moves.len())]);
+    }
+
+    let mut moves = vec![];
+    board.gen_moves(|m| moves.extend(m));
+    (!moves.is_empty()).then_some(board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::SmallRng};
+
+    fn material(board: &Board, color: Color) -> Vec<PieceType> {
+        let mut pieces: Vec<_> = board.occupied[color]
+            .into_iter()
+            .map(|sq| board.piece_on(sq).unwrap())
+            .collect();
+        pieces.sort_by_key(|pt| pt.idx());
+        pieces
+    }
+
+    #[test]
+    fn parse_signatures() {
+        let signature = MaterialSignature::parse("KRPvKR").unwrap();
+        assert_eq!(
+            signature.pieces[Color::White],
+            [PieceType::King, PieceType::Rook, PieceType::Pawn]
+        );
+        assert_eq!(
+            signature.pieces[Color::Black],
+            [PieceType::King, PieceType::Rook]
+        );
+
+        assert!(MaterialSignature::parse("KvK").is_some());
+        for invalid in [
+            "KRPKR",
+            "KRvR",
+            "KKvK",
+            "KRxvK",
+            "KrvK",
+            "KQQQQQQQQQQQQQQQQvK",
+        ] {
+            assert!(MaterialSignature::parse(invalid).is_none(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn positions_match_signature() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+
+        for s in ["KvK", "KRPvKR", "KQvKNN", "KPPPPPPPPvKPPPPPPPP", "KBBvKN"] {
+            let signature = MaterialSignature::parse(s).unwrap();
+            for _ in 0..200 {
+                let board = random_position(&mut rng, &signature).unwrap();
+                assert_eq!(board.check_invariants(), Ok(()));
+                for color in [Color::White, Color::Black] {
+                    let mut expected = signature.pieces[color].clone();
+                    expected.sort_by_key(|pt| pt.idx());
+                    assert_eq!(material(&board, color), expected, "{s}");
+                }
+            }
+        }
+
+        // Positions are reproducible from the seed.
+        let signature = MaterialSignature::parse("KRPvKR").unwrap();
+        let fens = |seed| {
+            let mut rng = SmallRng::seed_from_u64(seed);
+            (0..10)
+                .map(|_| random_position(&mut rng, &signature).unwrap().fen(false))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(fens(1), fens(1));
+        assert_ne!(fens(1), fens(2));
+    }
+
+    #[test]
+    fn playouts_stop_at_fifty_moves() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+
+        // With only one piece to capture and no pawns, 300 plies can't fit in the fifty-move rule.
+        let start = Board::read_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        for _ in 0..20 {
+            assert!(random_playout(&mut rng, &start, 300).is_none());
+        }
+
+        for _ in 0..20 {
+            if let Some(board) = random_playout(&mut rng, &Board::start_pos(), 300) {
+                assert!(board.halfmove_clock < 100);
+                assert_eq!(board.check_invariants(), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn impossible_signatures() {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+        // 30 pawns fit on the 48 squares pawns may go on, but 50 don't.
+        let pawns = format!("K{}vK{}", "P".repeat(15), "P".repeat(15));
+        let signature = MaterialSignature::parse(&pawns).unwrap();
+        assert!(random_position(&mut rng, &signature).is_some());
+
+        let mut signature = signature;
+        signature.pieces[Color::White].extend([PieceType::Pawn; 20]);
+        assert!(random_position(&mut rng, &signature).is_none());
+    }
+}
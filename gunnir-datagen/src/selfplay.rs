@@ -0,0 +1,340 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use rand::{SeedableRng, rngs::SmallRng};
+
+use crate::*;
+
+/// Openings whose verification search scores worse than this for either side are thrown away,
+/// since their games wouldn't teach us much.
+const MAX_OPENING_SCORE: i32 = 1000;
+
+/// A game is adjudicated as a win once the search agrees on a score of at least
+/// `WIN_SCORE` for the same side for `WIN_PLIES` plies in a row.
+const WIN_SCORE: i32 = 2500;
+const WIN_PLIES: u32 = 6;
+
+/// After `DRAW_MIN_PLY`, a game is adjudicated as a draw once the score stays within
+/// `DRAW_SCORE` of zero for `DRAW_PLIES` plies in a row.
+const DRAW_MIN_PLY: usize = 80;
+const DRAW_SCORE: i32 = 10;
+const DRAW_PLIES: u32 = 10;
+
+/// Games that get this long are called a draw.
+const MAX_GAME_PLIES: usize = 400;
+
+const TT_MB: usize = 16;
+
+#[derive(Clone, Copy, Debug)]
+pub struct DatagenConfig {
+    pub games: u64,
+    pub threads: usize,
+    /// Every game is seeded from this and its index, so a run is reproducible regardless of the
+    /// number of threads.
+    pub seed: u64,
+    /// The number of random plies played to get the opening position.
+    pub random_plies: usize,
+    /// Whether to start from random Chess960 positions instead of the classical one.
+    pub chess960: bool,
+    /// The node budget for every move's search.
+    pub nodes: u64,
+}
+
+impl Default for DatagenConfig {
+    fn default() -> Self {
+        Self {
+            games: 1000,
+            threads: 1,
+            seed: 0,
+            random_plies: 8,
+            chess960: false,
+            nodes: 5000,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct DatagenStats {
+    pub games: u64,
+    pub positions: u64,
+    pub white_wins: u64,
+    pub draws: u64,
+    pub black_wins: u64,
+}
+
+/// Derives the seed of a game from the seed of the run, by one round of SplitMix64.
+fn game_seed(seed: u64, game: u64) -> u64 {
+    let mut z = seed.wrapping_add(game.wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Whether neither side can possibly checkmate: only kings, plus at most one minor piece.
+fn insufficient_material(board: &Board) -> bool {
+    let minors = board.pieces[PieceType::Knight] | board.pieces[PieceType::Bishop];
+    let count = board.occupied().popcnt();
+    count == 2 || (count == 3 && minors.is_non_empty())
+}
+
+/// Whether the position occurred before with the same side to move, since the last
+/// irreversible move. `history` holds the hashes of all previous positions of the game.
+fn is_repetition(board: &Board, history: &[u64]) -> bool {
+    history
+        .iter()
+        .rev()
+        .take(board.halfmove_clock as usize)
+        .skip(1)
+        .step_by(2)
+        .any(|&h| h == board.hash)
+}
+
+/// Captures and promotions. Positions whose best move is one of these are too sharp for a
+/// static evaluation to learn from.
+fn is_tactical(board: &Board, mov: Move) -> bool {
+    match mov.move_flag() {
+        MoveFlag::None => board.occupied().contains(mov.to()),
+        MoveFlag::Castle => false,
+        MoveFlag::EnPassant | MoveFlag::Promotion => true,
+    }
+}
+
+/// Plays one game of self-play and returns its result and quiet positions. The searcher's state
+/// is cleared first, so the game only depends on `seed`.
+pub fn play_game(
+    searcher: &mut Searcher,
+    config: &DatagenConfig,
+    seed: u64,
+) -> (GameResult, Vec<Record>) {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let limits = SearchLimits {
+        nodes: Some(config.nodes),
+        ..Default::default()
+    };
+
+    let mut board = loop {
+        let Some(board) = random_opening(&mut rng, config.random_plies, config.chess960) else {
+            continue;
+        };
+
+        searcher.clear();
+        let info = searcher.search(&board, &[], limits, |_| {});
+        if info.score.abs() <= MAX_OPENING_SCORE {
+            break board;
+        }
+    };
+
+    let mut history = vec![];
+    let mut positions = vec![];
+    let mut win_streak: i32 = 0;
+    let mut draw_plies = 0;
+
+    let result = loop {
+        let mut moves = vec![];
+        board.gen_moves(|m| moves.extend(m));
+        if moves.is_empty() {
+            break match board.checkers.is_non_empty() {
+                true => GameResult::win_for(!board.stm),
+                false => GameResult::Draw,
+            };
+        }
+
+        if board.halfmove_clock >= 100
+            || is_repetition(&board, &history)
+            || insufficient_material(&board)
+            || history.len() >= MAX_GAME_PLIES
+        {
+            break GameResult::Draw;
+        }
+
+        let info = searcher.search(&board, &history, limits, |_| {});
+        let mov = info.best_move().unwrap();
+        let score = match board.stm {
+            Color::White => info.score,
+            Color::Black => -info.score,
+        };
+
+        if board.checkers.is_empty() && !is_tactical(&board, mov) && score.abs() < MATE_BOUND {
+            positions.push((board, score as i16));
+        }
+
+        // Positive while white is winning, negative while black is.
+        win_streak = if score >= WIN_SCORE {
+            win_streak.max(0) + 1
+        } else if score <= -WIN_SCORE {
+            win_streak.min(0) - 1
+        } else {
+            0
+        };
+        draw_plies = match history.len() >= DRAW_MIN_PLY && score.abs() <= DRAW_SCORE {
+            true => draw_plies + 1,
+            false => 0,
+        };
+
+        if win_streak.unsigned_abs() >= WIN_PLIES {
+            break GameResult::win_for(if win_streak > 0 {
+                Color::White
+            } else {
+                Color::Black
+            });
+        }
+        if draw_plies >= DRAW_PLIES {
+            break GameResult::Draw;
+        }
+
+        history.push(board.hash);
+        board.make_move(mov);
+    };
+
+    let records = positions
+        .into_iter()
+        .map(|(board, score)| Record {
+            board,
+            score,
+            result,
+        })
+        .collect();
+    (result, records)
+}
+
+/// Plays `config.games` games on `config.threads` threads and writes their positions to `out`,
+/// in the order of the games regardless of which thread finishes first. `progress` is called
+/// after every game written.
+pub fn generate(
+    config: &DatagenConfig,
+    out: &mut impl Write,
+    format: OutputFormat,
+    mut progress: impl FnMut(&DatagenStats),
+) -> io::Result<DatagenStats> {
+    let next_game = AtomicU64::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut stats = DatagenStats::default();
+
+    thread::scope(|s| {
+        for _ in 0..config.threads.max(1) {
+            let sender = sender.clone();
+            let next_game = &next_game;
+            s.spawn(move || {
+                let mut searcher = Searcher::new(TT_MB);
+                loop {
+                    let game = next_game.fetch_add(1, Ordering::Relaxed);
+                    if game >= config.games {
+                        break;
+                    }
+                    let played = play_game(&mut searcher, config, game_seed(config.seed, game));
+                    if sender.send((game, played)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // Games that finished before some earlier game did.
+        let mut pending = BTreeMap::new();
+        for (game, played) in receiver {
+            pending.insert(game, played);
+
+            while let Some((result, records)) = pending.remove(&stats.games) {
+                for record in &records {
+                    record.write(out, format, config.chess960)?;
+                }
+
+                stats.games += 1;
+                stats.positions += records.len() as u64;
+                // Not taken from the records, since a game may not have produced any.
+                match result {
+                    GameResult::WhiteWin => stats.white_wins += 1,
+                    GameResult::BlackWin => stats.black_wins += 1,
+                    GameResult::Draw => stats.draws += 1,
+                }
+                progress(&stats);
+            }
+        }
+
+        io::Result::Ok(())
+    })?;
+
+    out.flush()?;
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: DatagenConfig = DatagenConfig {
+        games: 3,
+        threads: 1,
+        seed: 42,
+        random_plies: 8,
+        chess960: false,
+        nodes: 300,
+    };
+
+    fn run(config: DatagenConfig, format: OutputFormat) -> Vec<u8> {
+        let mut out = vec![];
+        let stats = generate(&config, &mut out, format, |_| {}).unwrap();
+        assert_eq!(stats.games, config.games);
+
+        let mut searcher = Searcher::new(TT_MB);
+        let mut expected = DatagenStats::default();
+        for game in 0..config.games {
+            match play_game(&mut searcher, &config, game_seed(config.seed, game)).0 {
+                GameResult::WhiteWin => expected.white_wins += 1,
+                GameResult::BlackWin => expected.black_wins += 1,
+                GameResult::Draw => expected.draws += 1,
+            }
+        }
+        assert_eq!(
+            (stats.white_wins, stats.draws, stats.black_wins),
+            (expected.white_wins, expected.draws, expected.black_wins)
+        );
+        out
+    }
+
+    #[test]
+    fn reproducible() {
+        let a = run(CONFIG, OutputFormat::Binary);
+        let b = run(
+            DatagenConfig {
+                threads: 2,
+                ..CONFIG
+            },
+            OutputFormat::Binary,
+        );
+        assert!(!a.is_empty());
+        assert_eq!(a, b);
+
+        let c = run(DatagenConfig { seed: 43, ..CONFIG }, OutputFormat::Binary);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn quiet_positions() {
+        let text = run(
+            DatagenConfig {
+                chess960: true,
+                ..CONFIG
+            },
+            OutputFormat::Text,
+        );
+
+        for line in String::from_utf8(text).unwrap().lines() {
+            let [fen, score, result] = line.split(" | ").collect::<Vec<_>>()[..] else {
+                panic!("Malformed line {line}");
+            };
+            let board = Board::read_fen(fen).unwrap();
+            assert!(board.checkers.is_empty());
+            assert!(score.parse::<i32>().unwrap().abs() < MATE_BOUND);
+            assert!(["0.0", "0.5", "1.0"].contains(&result));
+        }
+    }
+}
@@ -0,0 +1,12 @@
+pub use gunnir_board::*;
+
+mod movepick;
+pub mod options;
+pub mod search;
+pub mod time;
+pub mod tt;
+
+pub use options::*;
+pub use search::*;
+pub use time::*;
+pub use tt::*;
@@ -0,0 +1,101 @@
+use crate::*;
+
+/// No legal chess position has more than 218 legal moves.
+const MAX_MOVES: usize = 256;
+
+/// Rough piece values, only used to order captures. Indexed by `PieceType::idx()`.
+pub(crate) const ORDERING_VALUES: [i32; PieceType::COUNT] = [320, 330, 500, 900, 100, 0];
+
+/// A list of moves alongside their ordering scores. Moves are picked lazily by selection sort,
+/// since we usually only look at the first few moves before getting a cutoff.
+pub(crate) struct MoveList {
+    moves: [(Move, i32); MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            moves: [(Move::new(Square::A1, Square::B1, MoveFlag::None), 0); MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub fn push(&mut self, mov: Move) {
+        self.moves[self.len] = (mov, 0);
+        self.len += 1;
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn score_with(&mut self, mut score: impl FnMut(Move) -> i32) {
+        for (mov, s) in &mut self.moves[..self.len] {
+            *s = score(*mov);
+        }
+    }
+
+    #[inline]
+    pub fn retain(&mut self, mut keep: impl FnMut(Move) -> bool) {
+        let mut len = 0;
+        for i in 0..self.len {
+            if keep(self.moves[i].0) {
+                self.moves[len] = self.moves[i];
+                len += 1;
+            }
+        }
+        self.len = len;
+    }
+
+    /// Moves the best scored move in `i..len` to index `i` and returns it.
+    #[inline]
+    pub fn pick(&mut self, i: usize) -> Move {
+        let best = (i..self.len).max_by_key(|&j| self.moves[j].1).unwrap();
+        self.moves.swap(i, best);
+        self.moves[i].0
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[(Move, i32)] {
+        &self.moves[..self.len]
+    }
+}
+
+#[inline]
+pub(crate) fn is_capture(board: &Board, mov: Move) -> bool {
+    match mov.move_flag() {
+        MoveFlag::EnPassant => true,
+        // In Chess960 the king may castle onto a square occupied by its own rook.
+        MoveFlag::Castle => false,
+        _ => board.piece_on(mov.to()).is_some(),
+    }
+}
+
+/// Captures and promotions. Everything else is considered quiet.
+#[inline]
+pub(crate) fn is_tactical(board: &Board, mov: Move) -> bool {
+    mov.move_flag() == MoveFlag::Promotion || is_capture(board, mov)
+}
+
+/// Most valuable victim, least valuable attacker. Promotions are scored as captures of the
+/// promoted piece, so under-promotions end up behind most other tactical moves.
+#[inline]
+pub(crate) fn mvv_lva(board: &Board, mov: Move) -> i32 {
+    let victim = match mov.move_flag() {
+        MoveFlag::EnPassant => PieceType::Pawn,
+        _ if is_capture(board, mov) => board.piece_on(mov.to()).unwrap(),
+        _ => {
+            return mov
+                .promotes_to()
+                .map_or(0, |pt| ORDERING_VALUES[pt.idx() as usize]);
+        }
+    };
+    let attacker = board.piece_on(mov.from()).unwrap();
+
+    16 * ORDERING_VALUES[victim.idx() as usize] - ORDERING_VALUES[attacker.idx() as usize] / 16
+}
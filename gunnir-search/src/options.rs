@@ -0,0 +1,141 @@
+/// Engine options that influence the search. All selective parts of the search are enabled
+/// by default, turning them off individually lets us measure what each of them is worth in
+/// self-play.
+#[derive(Clone, Copy, Debug)]
+pub struct SearchOptions {
+    pub null_move_pruning: bool,
+    /// Re-search null move cutoffs at reduced depth without null moves when the side to move
+    /// has little material left, since zugzwang makes the null move observation unreliable.
+    pub null_move_verification: bool,
+    pub late_move_reductions: bool,
+    pub reverse_futility_pruning: bool,
+    pub futility_pruning: bool,
+    pub late_move_pruning: bool,
+    pub razoring: bool,
+    pub check_extensions: bool,
+    pub singular_extensions: bool,
+    /// Evaluate with the neural network instead of the handcrafted evaluation.
+    pub use_nnue: bool,
+    /// Time in milliseconds we reserve per move for communication with the GUI.
+    pub move_overhead: u32,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            null_move_pruning: true,
+            null_move_verification: true,
+            late_move_reductions: true,
+            reverse_futility_pruning: true,
+            futility_pruning: true,
+            late_move_pruning: true,
+            razoring: true,
+            check_extensions: true,
+            singular_extensions: true,
+            use_nnue: false,
+            move_overhead: 10,
+        }
+    }
+}
+
+enum OptionField {
+    Check(fn(&mut SearchOptions) -> &mut bool),
+    Spin {
+        field: fn(&mut SearchOptions) -> &mut u32,
+        min: u32,
+        max: u32,
+    },
+}
+
+impl SearchOptions {
+    /// The UCI names of all options, alongside the fields they control.
+    const OPTIONS: &[(&str, OptionField)] = &[
+        (
+            "NullMovePruning",
+            OptionField::Check(|o| &mut o.null_move_pruning),
+        ),
+        (
+            "NullMoveVerification",
+            OptionField::Check(|o| &mut o.null_move_verification),
+        ),
+        (
+            "LateMoveReductions",
+            OptionField::Check(|o| &mut o.late_move_reductions),
+        ),
+        (
+            "ReverseFutilityPruning",
+            OptionField::Check(|o| &mut o.reverse_futility_pruning),
+        ),
+        (
+            "FutilityPruning",
+            OptionField::Check(|o| &mut o.futility_pruning),
+        ),
+        (
+            "LateMovePruning",
+            OptionField::Check(|o| &mut o.late_move_pruning),
+        ),
+        ("Razoring", OptionField::Check(|o| &mut o.razoring)),
+        (
+            "CheckExtensions",
+            OptionField::Check(|o| &mut o.check_extensions),
+        ),
+        (
+            "SingularExtensions",
+            OptionField::Check(|o| &mut o.singular_extensions),
+        ),
+        ("UseNNUE", OptionField::Check(|o| &mut o.use_nnue)),
+        (
+            "MoveOverhead",
+            OptionField::Spin {
+                field: |o| &mut o.move_overhead,
+                min: 0,
+                max: 5000,
+            },
+        ),
+    ];
+
+    /// Sets the option called `name` (case insensitive, as in UCI) to `value`. Returns `None`
+    /// if either the name or the value is invalid.
+    pub fn set(&mut self, name: &str, value: &str) -> Option<()> {
+        let (_, field) = Self::OPTIONS
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))?;
+
+        match *field {
+            OptionField::Check(field) => {
+                *field(self) = match value.trim() {
+                    "true" => true,
+                    "false" => false,
+                    _ => return None,
+                };
+            }
+            OptionField::Spin { field, min, max } => {
+                let value = value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|v| (min..=max).contains(v))?;
+                *field(self) = value;
+            }
+        }
+
+        Some(())
+    }
+
+    /// Returns the `option` lines to print in response to the `uci` command.
+    pub fn uci_options(&self) -> impl Iterator<Item = String> {
+        let mut defaults = Self::default();
+        Self::OPTIONS.iter().map(move |(name, field)| match *field {
+            OptionField::Check(field) => {
+                format!(
+                    "option name {name} type check default {}",
+                    field(&mut defaults)
+                )
+            }
+            OptionField::Spin { field, min, max } => format!(
+                "option name {name} type spin default {} min {min} max {max}",
+                field(&mut defaults)
+            ),
+        })
+    }
+}
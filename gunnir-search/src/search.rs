@@ -0,0 +1,872 @@
+use std::time::Duration;
+
+use crate::movepick::*;
+use crate::*;
+
+pub const MAX_PLY: usize = 128;
+
+pub const INFINITY: i32 = 32001;
+pub const MATE: i32 = 32000;
+/// Any score with an absolute value above this bound is a mate score.
+pub const MATE_BOUND: i32 = MATE - MAX_PLY as i32;
+
+const RFP_MAX_DEPTH: i32 = 8;
+const RFP_MARGIN: i32 = 80;
+
+const RAZOR_MAX_DEPTH: i32 = 3;
+const RAZOR_MARGIN: i32 = 250;
+
+const NMP_MIN_DEPTH: i32 = 3;
+/// Above this depth we verify null move cutoffs even if we don't suspect a zugzwang, since
+/// a wrong cutoff this close to the root is very expensive.
+const NMP_VERIFY_DEPTH: i32 = 12;
+
+const FUTILITY_MAX_DEPTH: i32 = 8;
+const FUTILITY_BASE: i32 = 100;
+const FUTILITY_MARGIN: i32 = 100;
+
+const LMP_MAX_DEPTH: i32 = 8;
+
+const LMR_MIN_DEPTH: i32 = 3;
+
+const SE_MIN_DEPTH: i32 = 8;
+
+const MAX_HISTORY: i32 = 16384;
+
+/// We only look at the clock every this many nodes, since reading it isn't free.
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// The limits of a search, as given by the UCI `go` command.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+}
+
+impl SearchLimits {
+    /// Parses the arguments of a UCI `go` command, e.g. `wtime 1000 btime 1000 movestogo 5`.
+    /// Arguments we don't support (like `ponder` or `searchmoves`) are ignored, while `infinite`
+    /// is the same as not giving any limits at all.
+    pub fn parse_go(args: &str) -> Option<Self> {
+        let mut limits = Self::default();
+        let mut tokens = args.split_ascii_whitespace();
+
+        while let Some(token) = tokens.next() {
+            let mut millis = || -> Option<Option<Duration>> {
+                let ms: i64 = tokens.next()?.parse().ok()?;
+                // Some GUIs send negative times when we're about to flag.
+                Some(Some(Duration::from_millis(ms.max(0) as u64)))
+            };
+
+            match token {
+                "movetime" => limits.movetime = millis()?,
+                "wtime" => limits.wtime = millis()?,
+                "btime" => limits.btime = millis()?,
+                "winc" => limits.winc = millis()?,
+                "binc" => limits.binc = millis()?,
+                "depth" => limits.depth = Some(tokens.next()?.parse().ok()?),
+                "nodes" => limits.nodes = Some(tokens.next()?.parse().ok()?),
+                "movestogo" => limits.movestogo = Some(tokens.next()?.parse().ok()?),
+                _ => {}
+            }
+        }
+
+        Some(limits)
+    }
+}
+
+/// The result of a completed iteration of iterative deepening.
+#[derive(Clone, Default, Debug)]
+pub struct SearchInfo {
+    pub depth: u8,
+    pub seldepth: u8,
+    /// The score from the point of view of the side to move, in centipawns.
+    pub score: i32,
+    pub nodes: u64,
+    pub pv: Vec<Move>,
+}
+
+impl SearchInfo {
+    #[inline]
+    pub fn best_move(&self) -> Option<Move> {
+        self.pv.first().copied()
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct StackEntry {
+    /// `None` if the side to move is in check.
+    static_eval: Option<i32>,
+    /// During a singular extension search, the move we're trying to prove singular.
+    excluded: Option<Move>,
+    /// Whether we got to this ply by a null move.
+    null_move: bool,
+}
+
+pub struct Searcher {
+    pub options: SearchOptions,
+    tt: TranspositionTable,
+    pawns: PawnTable,
+    /// NNUE accumulators for the positions on the current search path, indexed by ply. Only
+    /// kept up to date if `options.use_nnue` is set.
+    accumulators: Vec<Accumulator>,
+    /// Butterfly history, indexed by side to move and the move's from and to squares.
+    history: Box<[[[i32; Square::COUNT]; Square::COUNT]; Color::COUNT]>,
+    killers: [[Option<Move>; 2]; MAX_PLY + 1],
+    stack: [StackEntry; MAX_PLY + 1],
+    /// Triangular PV table.
+    pv: Box<[[Option<Move>; MAX_PLY + 1]; MAX_PLY + 1]>,
+    pv_len: [usize; MAX_PLY + 1],
+    /// Late move reductions, indexed by depth and number of moves searched.
+    lmr: Box<[[i32; 64]; 64]>,
+    /// Hashes of all positions since the start of the game, for repetition detection.
+    hashes: Vec<u64>,
+    /// Null moves are disabled before this ply, which we use for null move verification.
+    nmp_min_ply: usize,
+    limits: SearchLimits,
+    time: TimeManager,
+    nodes: u64,
+    seldepth: usize,
+    completed_depth: u8,
+    stopped: bool,
+}
+
+impl Searcher {
+    pub fn new(tt_mb: usize) -> Self {
+        let mut lmr = Box::new([[0; 64]; 64]);
+        for (depth, row) in lmr.iter_mut().enumerate().skip(1) {
+            for (moves, r) in row.iter_mut().enumerate().skip(1) {
+                *r = (0.75 + (depth as f64).ln() * (moves as f64).ln() / 2.25) as i32;
+            }
+        }
+
+        Self {
+            options: SearchOptions::default(),
+            tt: TranspositionTable::new(tt_mb),
+            pawns: PawnTable::default(),
+            accumulators: Vec::new(),
+            history: Box::new([[[0; Square::COUNT]; Square::COUNT]; Color::COUNT]),
+            killers: [[None; 2]; MAX_PLY + 1],
+            stack: [StackEntry::default(); MAX_PLY + 1],
+            pv: Box::new([[None; MAX_PLY + 1]; MAX_PLY + 1]),
+            pv_len: [0; MAX_PLY + 1],
+            lmr,
+            hashes: Vec::new(),
+            nmp_min_ply: 0,
+            limits: SearchLimits::default(),
+            time: TimeManager::infinite(),
+            nodes: 0,
+            seldepth: 0,
+            completed_depth: 0,
+            stopped: false,
+        }
+    }
+
+    /// Forgets everything learned from previous searches. Should be called between games.
+    pub fn clear(&mut self) {
+        self.tt.clear();
+        self.history.iter_mut().flatten().for_each(|h| h.fill(0));
+        self.killers = [[None; 2]; MAX_PLY + 1];
+    }
+
+    /// Searches `board` by iterative deepening until one of the `limits` is reached, calling
+    /// `report` after every completed iteration. `history` contains the hashes of all positions
+    /// preceding `board` in the game, which are needed to detect repetitions.
+    pub fn search(
+        &mut self,
+        board: &Board,
+        history: &[u64],
+        limits: SearchLimits,
+        report: impl FnMut(&SearchInfo),
+    ) -> SearchInfo {
+        self.search_with_clock(board, history, limits, SystemClock::default(), report)
+    }
+
+    /// Like `search`, but measures time with the given clock instead of the system clock.
+    pub fn search_with_clock(
+        &mut self,
+        board: &Board,
+        history: &[u64],
+        limits: SearchLimits,
+        clock: impl Clock + Send + 'static,
+        mut report: impl FnMut(&SearchInfo),
+    ) -> SearchInfo {
+        let overhead = Duration::from_millis(self.options.move_overhead as u64);
+        self.time = TimeManager::new(clock, &limits, board.stm, overhead);
+        self.limits = limits;
+        self.nodes = 0;
+        self.completed_depth = 0;
+        self.stopped = false;
+        self.nmp_min_ply = 0;
+        self.stack = [StackEntry::default(); MAX_PLY + 1];
+
+        self.hashes.clear();
+        self.hashes.extend_from_slice(history);
+        self.hashes.push(board.hash);
+
+        self.accumulators.clear();
+        if self.options.use_nnue {
+            let root = Accumulator::new(&DEFAULT_NETWORK, board);
+            self.accumulators.resize(MAX_PLY + 1, root);
+        }
+
+        let max_depth = limits
+            .depth
+            .map_or(MAX_PLY - 1, |d| d as usize)
+            .clamp(1, MAX_PLY - 1);
+
+        let mut info = SearchInfo::default();
+        for depth in 1..=max_depth as u8 {
+            self.seldepth = 0;
+            let score = self.negamax(board, depth as i32, 0, -INFINITY, INFINITY);
+            if self.stopped {
+                break;
+            }
+
+            self.completed_depth = depth;
+            info = SearchInfo {
+                depth,
+                seldepth: self.seldepth as u8,
+                score,
+                nodes: self.nodes,
+                pv: self.pv[0][..self.pv_len[0]]
+                    .iter()
+                    .map_while(|&m| m)
+                    .collect(),
+            };
+            report(&info);
+
+            // Without any legal moves there is nothing to search.
+            if info.pv.is_empty()
+                || self.limits.nodes.is_some_and(|n| self.nodes >= n)
+                || !self.time.should_start_iteration(&info)
+            {
+                break;
+            }
+        }
+
+        info.nodes = self.nodes;
+        info
+    }
+
+    #[inline]
+    fn should_stop(&mut self) -> bool {
+        // We always finish the first iteration, so that we have a move to play.
+        if !self.stopped && self.completed_depth > 0 {
+            self.stopped = self.limits.nodes.is_some_and(|n| self.nodes >= n)
+                || (self.nodes.is_multiple_of(TIME_CHECK_INTERVAL)
+                    && self.time.hard_limit_reached());
+        }
+        self.stopped
+    }
+
+    fn is_draw(&self, board: &Board) -> bool {
+        if board.halfmove_clock >= 100 {
+            return true;
+        }
+
+        // A repetition can only have happened since the last irreversible move, and only
+        // with the same side to move. The last entry is the current position itself.
+        self.hashes
+            .iter()
+            .rev()
+            .take(board.halfmove_clock as usize + 1)
+            .skip(2)
+            .step_by(2)
+            .any(|&h| h == board.hash)
+    }
+
+    #[inline]
+    fn evaluate(&mut self, board: &Board, ply: usize) -> i32 {
+        if self.options.use_nnue {
+            DEFAULT_NETWORK.evaluate(&self.accumulators[ply], board.stm)
+        } else {
+            evaluate_cached(board, &mut self.pawns)
+        }
+    }
+
+    /// Derives the accumulator at `ply + 1` from its parent's, given the pieces the move in
+    /// between added and removed.
+    #[inline]
+    fn update_accumulator(&mut self, ply: usize, updates: &PieceUpdates) {
+        if self.options.use_nnue {
+            let (parents, children) = self.accumulators.split_at_mut(ply + 1);
+            children[0].clone_from(&parents[ply]);
+            children[0].update(&DEFAULT_NETWORK, updates);
+        }
+    }
+
+    #[inline]
+    fn update_pv(&mut self, ply: usize, mov: Move) {
+        let child_len = self.pv_len[ply + 1].max(ply + 1);
+        let (head, tail) = self.pv.split_at_mut(ply + 1);
+        head[ply][ply] = Some(mov);
+        head[ply][ply + 1..child_len].copy_from_slice(&tail[0][ply + 1..child_len]);
+        self.pv_len[ply] = child_len;
+    }
+
+    #[inline]
+    fn update_history(&mut self, stm: Color, mov: Move, bonus: i32) {
+        let entry = &mut self.history[stm.idx() as usize][mov.from().idx() as usize]
+            [mov.to().idx() as usize];
+        // Gravity keeps the entries bounded by `MAX_HISTORY`.
+        *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+    }
+
+    fn negamax(
+        &mut self,
+        board: &Board,
+        depth: i32,
+        ply: usize,
+        mut alpha: i32,
+        mut beta: i32,
+    ) -> i32 {
+        if depth <= 0 {
+            return self.qsearch(board, ply, alpha, beta);
+        }
+
+        let pv_node = beta - alpha > 1;
+        let root = ply == 0;
+        let opts = self.options;
+
+        self.pv_len[ply] = ply;
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        if self.should_stop() {
+            return 0;
+        }
+
+        let in_check = board.checkers.is_non_empty();
+
+        if !root {
+            if self.is_draw(board) {
+                return 0;
+            }
+            if ply >= MAX_PLY - 1 {
+                return if in_check {
+                    0
+                } else {
+                    self.evaluate(board, ply)
+                };
+            }
+
+            // Mate distance pruning: even mating on the next move can't beat a shorter mate
+            // found elsewhere in the tree.
+            alpha = alpha.max(-MATE + ply as i32);
+            beta = beta.min(MATE - ply as i32 - 1);
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+
+        let excluded = self.stack[ply].excluded;
+        let tt_entry = excluded
+            .is_none()
+            .then(|| self.tt.probe(board.hash))
+            .flatten();
+        let tt_move = tt_entry.and_then(|e| e.mov);
+        let tt_score = tt_entry.map(|e| score_from_tt(e.score as i32, ply));
+
+        if let Some(entry) = tt_entry
+            && let Some(score) = tt_score
+            && !pv_node
+            && entry.depth as i32 >= depth
+        {
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+                Bound::None => false,
+            };
+            if cutoff {
+                return score;
+            }
+        }
+
+        let static_eval = (!in_check).then(|| self.evaluate(board, ply));
+        self.stack[ply].static_eval = static_eval;
+        self.killers[ply + 1] = [None; 2];
+
+        // We're improving if our static eval got better since our last move. If it did, we
+        // expect fail highs to be more likely and can prune more aggressively.
+        let improving = match (static_eval, ply.checked_sub(2)) {
+            (Some(eval), Some(prev)) => self.stack[prev].static_eval.is_none_or(|p| eval > p),
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+
+        if let Some(eval) = static_eval
+            && !pv_node
+            && excluded.is_none()
+        {
+            // Reverse futility pruning: if we're so far ahead that even giving away a margin
+            // per remaining ply keeps us above beta, we assume the node fails high.
+            if opts.reverse_futility_pruning
+                && depth <= RFP_MAX_DEPTH
+                && beta.abs() < MATE_BOUND
+                && eval - RFP_MARGIN * (depth - improving as i32) >= beta
+            {
+                return eval;
+            }
+
+            // Razoring: if we're far below alpha near the leaves, only captures can save us,
+            // so we drop into quiescence search to check whether they do.
+            if opts.razoring && depth <= RAZOR_MAX_DEPTH && eval + RAZOR_MARGIN * depth <= alpha {
+                let score = self.qsearch(board, ply, alpha, alpha + 1);
+                if score <= alpha {
+                    return score;
+                }
+            }
+
+            // Null move pruning: if we're still above beta after passing the turn, an actual
+            // move will almost certainly be good enough too. This fails in zugzwang, so we skip
+            // it without any pieces and verify the cutoffs when we have few of them.
+            let non_pawns = board.occupied[board.stm]
+                .subtract(board.pieces[PieceType::Pawn] | board.pieces[PieceType::King])
+                .popcnt();
+
+            if opts.null_move_pruning
+                && depth >= NMP_MIN_DEPTH
+                && eval >= beta
+                && !self.stack[ply].null_move
+                && ply >= self.nmp_min_ply
+                && non_pawns > 0
+            {
+                let r = 3 + depth / 4 + ((eval - beta) / 200).min(3);
+
+                let mut child = *board;
+                child.make_null_move();
+                self.update_accumulator(ply, &PieceUpdates::default());
+                self.hashes.push(child.hash);
+                self.stack[ply + 1].null_move = true;
+                let score = -self.negamax(&child, depth - r, ply + 1, -beta, -beta + 1);
+                self.stack[ply + 1].null_move = false;
+                self.hashes.pop();
+
+                if self.stopped {
+                    return 0;
+                }
+
+                if score >= beta {
+                    // Null move searches can't prove mates, since passing isn't actually legal.
+                    let score = if score >= MATE_BOUND { beta } else { score };
+
+                    let zugzwang_prone = non_pawns <= 1;
+                    if !opts.null_move_verification || (!zugzwang_prone && depth < NMP_VERIFY_DEPTH)
+                    {
+                        return score;
+                    }
+
+                    // Verify with a reduced search of our own moves, not allowing further null
+                    // moves for the next few plies.
+                    self.nmp_min_ply = ply + (3 * (depth - r) / 4).max(0) as usize;
+                    let verified = self.negamax(board, depth - r, ply, beta - 1, beta);
+                    self.nmp_min_ply = 0;
+
+                    if verified >= beta {
+                        return score;
+                    }
+                }
+            }
+        }
+
+        let mut moves = MoveList::new();
+        board.gen_moves(|moves_| moves_.into_iter().for_each(|m| moves.push(m)));
+
+        let killers = self.killers[ply];
+        let history = &self.history[board.stm.idx() as usize];
+        moves.score_with(|mov| {
+            if Some(mov) == tt_move {
+                1 << 24
+            } else if is_tactical(board, mov) {
+                if mov.promotes_to().is_some_and(|pt| pt != PieceType::Queen) {
+                    -(1 << 20)
+                } else {
+                    (1 << 20) + mvv_lva(board, mov)
+                }
+            } else if killers[0] == Some(mov) {
+                (1 << 19) + 1
+            } else if killers[1] == Some(mov) {
+                1 << 19
+            } else {
+                history[mov.from().idx() as usize][mov.to().idx() as usize]
+            }
+        });
+
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        let mut moves_searched = 0;
+        let mut quiets_tried = MoveList::new();
+        let mut skip_quiets = false;
+
+        for i in 0..moves.len() {
+            let mov = moves.pick(i);
+            if Some(mov) == excluded {
+                continue;
+            }
+
+            let quiet = !is_tactical(board, mov);
+            if quiet && skip_quiets {
+                continue;
+            }
+
+            let is_killer = killers.contains(&Some(mov));
+            let lmr_base = self.lmr[depth.min(63) as usize][(moves_searched + 1).min(63)];
+
+            // Move loop pruning. We only prune once we know we're not getting mated, which
+            // also guarantees that at least one move has been searched.
+            if !root
+                && quiet
+                && best_score > -MATE_BOUND
+                && let Some(eval) = static_eval
+            {
+                // Late move pruning: quiet moves ordered this late rarely turn out best.
+                let lmp_threshold = (3 + depth * depth) / (2 - improving as i32);
+                if opts.late_move_pruning
+                    && depth <= LMP_MAX_DEPTH
+                    && quiets_tried.len() as i32 >= lmp_threshold
+                {
+                    skip_quiets = true;
+                    continue;
+                }
+
+                // Futility pruning: if even a generous positional gain can't lift us to alpha,
+                // quiet moves are hopeless.
+                let lmr_depth = (depth - 1 - lmr_base).max(0);
+                if opts.futility_pruning
+                    && depth <= FUTILITY_MAX_DEPTH
+                    && eval + FUTILITY_BASE + FUTILITY_MARGIN * lmr_depth <= alpha
+                {
+                    skip_quiets = true;
+                    continue;
+                }
+            }
+
+            let mut extension = 0;
+
+            // Singular extensions: if the TT move is much better than every alternative, it's
+            // likely to be forced and deserves to be searched deeper.
+            if opts.singular_extensions
+                && !root
+                && depth >= SE_MIN_DEPTH
+                && Some(mov) == tt_move
+                && excluded.is_none()
+                && let Some(entry) = tt_entry
+                && let Some(tt_score) = tt_score
+                && entry.depth as i32 >= depth - 3
+                && matches!(entry.bound, Bound::Lower | Bound::Exact)
+                && tt_score.abs() < MATE_BOUND
+            {
+                let singular_beta = tt_score - 2 * depth;
+
+                self.stack[ply].excluded = Some(mov);
+                let score = self.negamax(
+                    board,
+                    (depth - 1) / 2,
+                    ply,
+                    singular_beta - 1,
+                    singular_beta,
+                );
+                self.stack[ply].excluded = None;
+
+                if self.stopped {
+                    return 0;
+                }
+
+                if score < singular_beta {
+                    extension = 1;
+                } else if singular_beta >= beta {
+                    // Multi-cut: even without the TT move we fail high, so several moves
+                    // beat beta and this node will very likely fail high as well.
+                    return singular_beta;
+                }
+            }
+
+            let mut child = *board;
+            let mut updates = PieceUpdates::default();
+            child.make_move_with(mov, &mut updates);
+            self.update_accumulator(ply, &updates);
+            self.hashes.push(child.hash);
+            self.stack[ply + 1].null_move = false;
+
+            let gives_check = child.checkers.is_non_empty();
+            if extension == 0 && opts.check_extensions && gives_check {
+                extension = 1;
+            }
+
+            let new_depth = depth - 1 + extension;
+            moves_searched += 1;
+
+            let score = if moves_searched == 1 {
+                -self.negamax(&child, new_depth, ply + 1, -beta, -alpha)
+            } else {
+                // Late move reductions: later moves are searched at reduced depth with a null
+                // window, and only re-searched if they unexpectedly beat alpha.
+                let mut reduction = 0;
+                if opts.late_move_reductions && depth >= LMR_MIN_DEPTH && quiet && !in_check {
+                    reduction = lmr_base;
+                    reduction -= pv_node as i32;
+                    reduction += !improving as i32;
+                    reduction -= gives_check as i32;
+                    reduction -= is_killer as i32;
+                    reduction = reduction.min(new_depth - 1).max(0);
+                }
+
+                let mut score =
+                    -self.negamax(&child, new_depth - reduction, ply + 1, -alpha - 1, -alpha);
+                if score > alpha && reduction > 0 {
+                    score = -self.negamax(&child, new_depth, ply + 1, -alpha - 1, -alpha);
+                }
+                if score > alpha && score < beta {
+                    score = -self.negamax(&child, new_depth, ply + 1, -beta, -alpha);
+                }
+                score
+            };
+
+            self.hashes.pop();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+
+                if score > alpha {
+                    alpha = score;
+                    best_move = Some(mov);
+                    self.update_pv(ply, mov);
+
+                    if score >= beta {
+                        if quiet {
+                            if !is_killer {
+                                self.killers[ply] = [Some(mov), self.killers[ply][0]];
+                            }
+
+                            let bonus = (depth * depth).min(1200);
+                            self.update_history(board.stm, mov, bonus);
+                            for &(tried, _) in quiets_tried.as_slice() {
+                                self.update_history(board.stm, tried, -bonus);
+                            }
+                        }
+                        break;
+                    }
+                }
+            }
+
+            if quiet {
+                quiets_tried.push(mov);
+            }
+        }
+
+        if moves_searched == 0 {
+            return if excluded.is_some() {
+                alpha
+            } else if in_check {
+                -MATE + ply as i32
+            } else {
+                0
+            };
+        }
+
+        if excluded.is_none() {
+            let bound = if best_score >= beta {
+                Bound::Lower
+            } else if best_move.is_some() {
+                Bound::Exact
+            } else {
+                Bound::Upper
+            };
+
+            self.tt.store(
+                board.hash,
+                best_move,
+                score_to_tt(best_score, ply),
+                static_eval.unwrap_or(0),
+                depth,
+                bound,
+            );
+        }
+
+        best_score
+    }
+
+    fn qsearch(&mut self, board: &Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv_len[ply] = ply;
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        if self.should_stop() {
+            return 0;
+        }
+
+        let in_check = board.checkers.is_non_empty();
+
+        if ply > 0 && self.is_draw(board) {
+            return 0;
+        }
+        if ply >= MAX_PLY - 1 {
+            return if in_check {
+                0
+            } else {
+                self.evaluate(board, ply)
+            };
+        }
+
+        // When in check, we have to consider all evasions and can't stand pat.
+        let mut best_score = if in_check {
+            -MATE + ply as i32
+        } else {
+            let stand_pat = self.evaluate(board, ply);
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+            stand_pat
+        };
+
+        let mut moves = MoveList::new();
+        board.gen_moves(|moves_| moves_.into_iter().for_each(|m| moves.push(m)));
+        if !in_check {
+            moves.retain(|mov| is_tactical(board, mov));
+        }
+        moves.score_with(|mov| mvv_lva(board, mov));
+
+        for i in 0..moves.len() {
+            let mov = moves.pick(i);
+
+            let mut child = *board;
+            let mut updates = PieceUpdates::default();
+            child.make_move_with(mov, &mut updates);
+            self.update_accumulator(ply, &updates);
+            self.hashes.push(child.hash);
+            let score = -self.qsearch(&child, ply + 1, -beta, -alpha);
+            self.hashes.pop();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, mov);
+                    if score >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        best_score
+    }
+}
+
+/// Mate scores are stored relative to the node they're found in, so they stay correct when
+/// the same position is reached at a different ply.
+#[inline]
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+#[inline]
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(fen: &str, options: SearchOptions, depth: u8) -> SearchInfo {
+        let board = Board::read_fen(fen).unwrap();
+        let mut searcher = Searcher::new(4);
+        searcher.options = options;
+        let limits = SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        };
+        searcher.search(&board, &[], limits, |_| {})
+    }
+
+    fn all_off() -> SearchOptions {
+        let mut options = SearchOptions::default();
+        for line in SearchOptions::default().uci_options() {
+            if line.contains("type check") {
+                let name = line.split(' ').nth(2).unwrap();
+                options.set(name, "false").unwrap();
+            }
+        }
+        options
+    }
+
+    #[test]
+    fn finds_mate_in_two() {
+        // 1. Qd5+ Ka6 2. cxb8=N#
+        const FEN: &str = "1rb4r/pkPp3p/1b1P3n/1Q6/N3Pp2/8/P1P3PP/7K w - - 1 1";
+
+        let nnue = SearchOptions {
+            use_nnue: true,
+            ..Default::default()
+        };
+
+        for options in [SearchOptions::default(), all_off(), nnue] {
+            let info = search(FEN, options, 5);
+            assert_eq!(info.score, MATE - 3);
+            assert_eq!(info.pv.len(), 3);
+        }
+    }
+
+    #[test]
+    fn recognizes_stalemate() {
+        let info = search(
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+            SearchOptions::default(),
+            3,
+        );
+        assert_eq!(info.score, 0);
+        assert!(info.best_move().is_none());
+    }
+
+    #[test]
+    fn node_limit() {
+        let board = Board::start_pos();
+        let mut searcher = Searcher::new(4);
+        let limits = SearchLimits::parse_go("nodes 10000").unwrap();
+        let info = searcher.search(&board, &[], limits, |_| {});
+
+        assert!(info.best_move().is_some());
+        assert!(info.nodes < 20_000);
+    }
+
+    #[test]
+    fn options() {
+        let mut options = SearchOptions::default();
+        assert!(options.set("nullmovepruning", "false").is_some());
+        assert!(!options.null_move_pruning);
+        assert!(options.set("NullMovePruning", "maybe").is_none());
+        assert!(options.set("Contempt", "true").is_none());
+        assert!(options.set("MoveOverhead", "100").is_some());
+        assert_eq!(options.move_overhead, 100);
+        assert!(options.set("MoveOverhead", "-1").is_none());
+        assert_eq!(options.uci_options().count(), 11);
+    }
+}
@@ -0,0 +1,351 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::*;
+
+/// If we have to assume the game goes on forever, we plan with this many moves remaining.
+const DEFAULT_MOVES_TO_GO: u32 = 25;
+
+/// We expect every iteration of iterative deepening to take about this many times as long
+/// as the previous one.
+const BRANCHING_FACTOR: f64 = 2.0;
+
+/// Scales the soft limit by how many iterations in a row returned the same best move. An
+/// unstable best move means the search hasn't settled yet, so it gets more time.
+const STABILITY_SCALE: [f64; 6] = [2.0, 1.5, 1.2, 1.0, 0.9, 0.8];
+
+/// A monotonic source of time. The search only ever asks for time differences, so the origin
+/// is arbitrary.
+pub trait Clock {
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemClock(Instant);
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// A clock that only moves when told to, for deterministic tests. Clones share the same time,
+/// so a test can keep one handle and pass another to the search. Optionally, every reading
+/// of the clock advances it by a fixed tick, which makes time based limits behave like
+/// (reproducible) node limits.
+#[derive(Clone, Default)]
+pub struct FakeClock {
+    nanos: Arc<AtomicU64>,
+    tick: Duration,
+}
+
+impl FakeClock {
+    pub fn ticking(tick: Duration) -> Self {
+        Self {
+            nanos: Default::default(),
+            tick,
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.nanos
+            .fetch_add(by.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Clock for FakeClock {
+    #[inline]
+    fn now(&self) -> Duration {
+        let nanos = self
+            .nanos
+            .fetch_add(self.tick.as_nanos() as u64, Ordering::Relaxed);
+        Duration::from_nanos(nanos)
+    }
+}
+
+/// Decides how long to think on a move. The soft limit is checked between iterations of
+/// iterative deepening and is scaled by how settled the search looks, while the hard limit
+/// aborts the search wherever it is.
+pub struct TimeManager {
+    clock: Box<dyn Clock + Send>,
+    start: Duration,
+    soft: Option<Duration>,
+    hard: Option<Duration>,
+    last_iteration: Duration,
+    best_move: Option<Move>,
+    stability: usize,
+    /// The score of the first iteration deep enough to be meaningful, to detect score drops.
+    reference_score: Option<i32>,
+}
+
+impl TimeManager {
+    /// Computes the limits for the side to move `stm`. `overhead` is subtracted from the
+    /// available time to account for communication latency with the GUI.
+    pub fn new(
+        clock: impl Clock + Send + 'static,
+        limits: &SearchLimits,
+        stm: Color,
+        overhead: Duration,
+    ) -> Self {
+        let start = clock.now();
+
+        let (time, inc) = match stm {
+            Color::White => (limits.wtime, limits.winc),
+            Color::Black => (limits.btime, limits.binc),
+        };
+
+        let (soft, hard) = if let Some(movetime) = limits.movetime {
+            let limit = movetime
+                .saturating_sub(overhead)
+                .max(Duration::from_millis(1));
+            (None, Some(limit))
+        } else if let Some(time) = time {
+            let inc = inc.unwrap_or_default();
+            let moves_to_go = limits.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).clamp(1, 50);
+            let available = time.saturating_sub(overhead).max(Duration::from_millis(1));
+
+            let hard = (available * 3 / 4).min((available / moves_to_go + inc) * 4);
+            let soft = (available / moves_to_go + inc * 3 / 4).min(hard);
+            (Some(soft), Some(hard))
+        } else {
+            (None, None)
+        };
+
+        Self {
+            clock: Box::new(clock),
+            start,
+            soft,
+            hard,
+            last_iteration: Duration::ZERO,
+            best_move: None,
+            stability: 0,
+            reference_score: None,
+        }
+    }
+
+    /// A time manager that never stops the search.
+    pub fn infinite() -> Self {
+        Self::new(
+            SystemClock::default(),
+            &SearchLimits::default(),
+            Color::White,
+            Duration::ZERO,
+        )
+    }
+
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.clock.now() - self.start
+    }
+
+    #[inline]
+    pub fn soft_limit(&self) -> Option<Duration> {
+        self.soft
+    }
+
+    #[inline]
+    pub fn hard_limit(&self) -> Option<Duration> {
+        self.hard
+    }
+
+    #[inline]
+    pub fn hard_limit_reached(&self) -> bool {
+        self.hard.is_some_and(|hard| self.elapsed() >= hard)
+    }
+
+    /// Called after every completed iteration. Returns whether it's worth starting another one,
+    /// which is not the case if we've used up the (scaled) soft limit, or if the next iteration
+    /// would likely get cut off by the hard limit anyway.
+    pub fn should_start_iteration(&mut self, info: &SearchInfo) -> bool {
+        let elapsed = self.elapsed();
+        let iteration = elapsed - self.last_iteration;
+        self.last_iteration = elapsed;
+
+        if info.best_move() == self.best_move {
+            self.stability += 1;
+        } else {
+            self.best_move = info.best_move();
+            self.stability = 0;
+        }
+
+        // The first few iterations are too shallow to tell us anything about the score.
+        if info.depth <= 4 {
+            self.reference_score = Some(info.score);
+        }
+        let score_drop = self.reference_score.map_or(0, |s| s - info.score);
+
+        let Some(hard) = self.hard else {
+            return true;
+        };
+
+        if elapsed.as_secs_f64() + iteration.as_secs_f64() * BRANCHING_FACTOR >= hard.as_secs_f64()
+        {
+            return false;
+        }
+
+        let Some(soft) = self.soft else {
+            return true;
+        };
+
+        // Losing half a pawn or more doubles the time we're willing to spend.
+        let score_scale = 1.0 + score_drop.clamp(0, 50) as f64 / 50.0;
+        let stability_scale = STABILITY_SCALE[self.stability.min(STABILITY_SCALE.len() - 1)];
+        let soft = soft.mul_f64(score_scale * stability_scale).min(hard);
+
+        elapsed < soft
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn info(depth: u8, score: i32, best_move: &str) -> SearchInfo {
+        let board = Board::start_pos();
+        SearchInfo {
+            depth,
+            score,
+            pv: vec![board.parse_move(best_move, false).unwrap()],
+            ..Default::default()
+        }
+    }
+
+    fn clock_limits(time: u64, inc: u64, movestogo: Option<u32>) -> SearchLimits {
+        SearchLimits {
+            wtime: Some(ms(time)),
+            winc: Some(ms(inc)),
+            btime: Some(ms(1)),
+            movestogo,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn movetime() {
+        let limits = SearchLimits::parse_go("movetime 1000").unwrap();
+        let tm = TimeManager::new(FakeClock::default(), &limits, Color::Black, ms(50));
+
+        assert_eq!(tm.soft_limit(), None);
+        assert_eq!(tm.hard_limit(), Some(ms(950)));
+    }
+
+    #[test]
+    fn limits_fit_clock() {
+        for (time, inc, mtg) in [
+            (60_000, 0, None),
+            (1000, 100, None),
+            (10, 0, None),
+            (5000, 0, Some(1)),
+        ] {
+            let limits = clock_limits(time, inc, mtg);
+            let tm = TimeManager::new(FakeClock::default(), &limits, Color::White, ms(10));
+
+            let (soft, hard) = (tm.soft_limit().unwrap(), tm.hard_limit().unwrap());
+            assert!(soft <= hard);
+            assert!(hard < ms(time));
+        }
+
+        // With only one move left until the time control, we can use most of our time.
+        let limits = clock_limits(5000, 0, Some(1));
+        let tm = TimeManager::new(FakeClock::default(), &limits, Color::White, ms(0));
+        assert_eq!(tm.hard_limit(), Some(ms(3750)));
+    }
+
+    #[test]
+    fn stops_at_hard_limit() {
+        let clock = FakeClock::default();
+        let limits = SearchLimits::parse_go("wtime 10000 btime 10000 winc 0 binc 0").unwrap();
+        let tm = TimeManager::new(clock.clone(), &limits, Color::White, ms(0));
+
+        let hard = tm.hard_limit().unwrap();
+        clock.advance(hard - ms(1));
+        assert!(!tm.hard_limit_reached());
+        clock.advance(ms(1));
+        assert!(tm.hard_limit_reached());
+    }
+
+    /// Runs iterations of the given lengths and returns how many get completed.
+    fn iterations(limits: &SearchLimits, lengths: &[u64], infos: &[SearchInfo]) -> usize {
+        let clock = FakeClock::default();
+        let mut tm = TimeManager::new(clock.clone(), limits, Color::White, ms(0));
+
+        for (i, (&len, info)) in lengths.iter().zip(infos).enumerate() {
+            clock.advance(ms(len));
+            if !tm.should_start_iteration(info) {
+                return i + 1;
+            }
+        }
+        lengths.len()
+    }
+
+    #[test]
+    fn stable_search_stops_at_soft_limit() {
+        // soft = 20000ms / 25 = 800ms, hard = 3200ms
+        let limits = clock_limits(20_000, 0, None);
+        let infos: Vec<_> = (1..=20).map(|d| info(d, 20, "e2e4")).collect();
+
+        assert_eq!(iterations(&limits, &[100; 20], &infos), 7);
+    }
+
+    #[test]
+    fn unstable_best_move_extends() {
+        let limits = clock_limits(20_000, 0, None);
+        let infos: Vec<_> = (1..=20)
+            .map(|d| info(d, 20, if d % 2 == 0 { "e2e4" } else { "d2d4" }))
+            .collect();
+
+        assert_eq!(iterations(&limits, &[100; 20], &infos), 16);
+    }
+
+    #[test]
+    fn score_drop_extends() {
+        let limits = clock_limits(20_000, 0, None);
+        let infos: Vec<_> = (1..=20)
+            .map(|d| info(d, if d <= 4 { 20 } else { -100 }, "e2e4"))
+            .collect();
+
+        assert_eq!(iterations(&limits, &[100; 20], &infos), 13);
+    }
+
+    #[test]
+    fn skips_iteration_that_cannot_finish() {
+        // hard = 100ms
+        let limits = SearchLimits::parse_go("movetime 100").unwrap();
+        let infos: Vec<_> = (1..=10).map(|d| info(d, 20, "e2e4")).collect();
+
+        // After 1 + 2 + ... + 32 = 63ms, the next iteration should take another 64ms.
+        assert_eq!(iterations(&limits, &[1, 2, 4, 8, 16, 32, 64], &infos), 6);
+    }
+
+    #[test]
+    fn search_is_reproducible() {
+        let board = Board::start_pos();
+        let limits = SearchLimits::parse_go("movetime 100").unwrap();
+
+        let run = || {
+            let mut searcher = Searcher::new(1);
+            let clock = FakeClock::ticking(Duration::from_millis(1));
+            searcher.search_with_clock(&board, &[], limits, clock, |_| {})
+        };
+
+        let (a, b) = (run(), run());
+        assert!(a.best_move().is_some());
+        assert_eq!(a.nodes, b.nodes);
+        assert_eq!(a.pv, b.pv);
+    }
+}
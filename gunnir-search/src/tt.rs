@@ -0,0 +1,84 @@
+use crate::*;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Bound {
+    #[default]
+    None,
+    /// The stored score is a lower bound on the true score (the node failed high).
+    Lower,
+    /// The stored score is an upper bound on the true score (the node failed low).
+    Upper,
+    /// The stored score is exact (a PV node).
+    Exact,
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TtEntry {
+    /// The lower 16 bits of the zobrist hash. The upper bits are already implied by the
+    /// index of the entry, so this is enough to make collisions very unlikely.
+    key: u16,
+    pub mov: Option<Move>,
+    pub score: i16,
+    pub eval: i16,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+pub struct TranspositionTable {
+    entries: Vec<TtEntry>,
+}
+
+impl TranspositionTable {
+    pub fn new(mb: usize) -> Self {
+        let len = (mb * 1024 * 1024 / size_of::<TtEntry>()).max(1);
+        Self {
+            entries: vec![TtEntry::default(); len],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(TtEntry::default());
+    }
+
+    #[inline]
+    fn index(&self, hash: u64) -> usize {
+        // Maps the hash uniformly onto the table without requiring a power of two length.
+        ((hash as u128 * self.entries.len() as u128) >> 64) as usize
+    }
+
+    #[inline]
+    pub fn probe(&self, hash: u64) -> Option<TtEntry> {
+        let entry = self.entries[self.index(hash)];
+        (entry.bound != Bound::None && entry.key == hash as u16).then_some(entry)
+    }
+
+    #[inline]
+    pub fn store(
+        &mut self,
+        hash: u64,
+        mov: Option<Move>,
+        score: i32,
+        eval: i32,
+        depth: i32,
+        bound: Bound,
+    ) {
+        let idx = self.index(hash);
+        let old = &mut self.entries[idx];
+
+        // Entries of other positions are always replaced, since there is no age to tell whether
+        // a deep entry is still useful. For the same position, keep a much deeper entry unless
+        // the new one is exact.
+        let same_position = old.key == hash as u16;
+        if !same_position || bound == Bound::Exact || depth + 2 >= old.depth as i32 {
+            *old = TtEntry {
+                key: hash as u16,
+                // Don't throw away a known good move just because this search didn't find one.
+                mov: mov.or(old.mov.filter(|_| same_position)),
+                score: score as i16,
+                eval: eval as i16,
+                depth: depth.clamp(0, u8::MAX as i32) as u8,
+                bound,
+            };
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::thread;
+
+use crate::*;
+
+/// A labeled position, reduced to what the tuner needs.
+pub struct Entry {
+    /// The sparse coefficients of the evaluation weights, see `Coefficients`.
+    pub coeffs: Box<[(u16, i16)]>,
+    /// The game phase, between 0 and `MAX_PHASE`.
+    pub phase: u8,
+    /// The game result from white's point of view: 1 for a win, 0.5 for a draw, 0 for a loss.
+    pub result: f32,
+}
+
+impl Entry {
+    pub fn new(board: &Board, result: f32) -> Self {
+        Self {
+            coeffs: Coefficients::of(board)
+                .into_iter()
+                .map(|(i, c)| (i as u16, c as i16))
+                .collect(),
+            phase: board.phase() as u8,
+            result,
+        }
+    }
+}
+
+/// Parses a game result from white's point of view, either as a number or in PGN notation.
+fn parse_result(s: &str) -> Option<f32> {
+    let s = s.trim().trim_start_matches('[').trim_end_matches(']');
+    let result = match s {
+        "1-0" => 1.0,
+        "0-1" => 0.0,
+        "1/2-1/2" => 0.5,
+        _ => s.parse().ok()?,
+    };
+    (0.0..=1.0).contains(&result).then_some(result)
+}
+
+/// Parses a line of the form `<fen> [1.0]` or `<fen> | 0.5`. Any fields between the FEN and the
+/// result separated by `|`, like a search score, are ignored.
+pub fn parse_line(line: &str) -> Option<(Board, f32)> {
+    let line = line.trim();
+
+    let (fen, result) = if line.contains('|') {
+        let (fen, rest) = line.split_once('|')?;
+        (fen, rest.rsplit('|').next()?)
+    } else {
+        let start = line.rfind('[')?;
+        line.split_at(start)
+    };
+
+    Some((Board::read_fen(fen.trim())?, parse_result(result)?))
+}
+
+pub struct Dataset {
+    pub entries: Vec<Entry>,
+    /// Lines that couldn't be parsed.
+    pub skipped: usize,
+}
+
+impl Dataset {
+    /// Parses a dataset with one position per line, see `parse_line`, on `threads` threads.
+    /// Empty lines are ignored.
+    pub fn parse(text: &str, threads: usize) -> Self {
+        let lines: Vec<_> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+        let chunk_len = lines.len().div_ceil(threads.max(1)).max(1);
+
+        let chunks: Vec<_> = thread::scope(|s| {
+            let handles: Vec<_> = lines
+                .chunks(chunk_len)
+                .map(|chunk| {
+                    s.spawn(|| {
+                        chunk
+                            .iter()
+                            .map(|line| {
+                                let (board, result) = parse_line(line)?;
+                                Some(Entry::new(&board, result))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut dataset = Self {
+            entries: Vec::with_capacity(lines.len()),
+            skipped: 0,
+        };
+        for entry in chunks.into_iter().flatten() {
+            match entry {
+                Some(entry) => dataset.entries.push(entry),
+                None => dataset.skipped += 1,
+            }
+        }
+
+        dataset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEN: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    #[test]
+    fn line_formats() {
+        for (line, expected) in [
+            (format!("{FEN} [1.0]"), 1.0),
+            (format!("{FEN} [0.5]"), 0.5),
+            (format!("{FEN} | 0"), 0.0),
+            (format!("{FEN} | 35 | 0.5"), 0.5),
+            (format!("{FEN} | 1-0"), 1.0),
+            (format!("  {FEN} [1/2-1/2]  "), 0.5),
+        ] {
+            let (board, result) = parse_line(&line).unwrap();
+            assert_eq!(board.fen(false), FEN);
+            assert_eq!(result, expected);
+        }
+
+        for line in [
+            FEN.to_string(),
+            format!("{FEN} [2.0]"),
+            format!("{FEN} | draw"),
+            "8/8/8/8/8/8/8/8 w - - 0 1 | 0.5".to_string(),
+        ] {
+            assert!(parse_line(&line).is_none(), "{line}");
+        }
+    }
+
+    #[test]
+    fn parse_dataset() {
+        let text = format!("{FEN} [1.0]\n\ngarbage\n{FEN} | 0.0\n");
+        let dataset = Dataset::parse(&text, 2);
+
+        assert_eq!(dataset.entries.len(), 2);
+        assert_eq!(dataset.skipped, 1);
+        assert_eq!(dataset.entries[1].result, 0.0);
+    }
+}
@@ -0,0 +1,66 @@
+use std::fmt::Write;
+
+use crate::*;
+
+/// Writes `weights` (in parameter vector order) as Rust constants, with the same names and
+/// types as the ones in `params`, so they can be pasted over the old values.
+pub fn emit_rust(weights: &[Score]) -> String {
+    assert_eq!(weights.len(), PARAM_COUNT);
+
+    let mut out = String::from("// Generated by `gunnir tune`.\n");
+
+    for &group in ParamGroup::ALL {
+        let values = &weights[group.offset()..group.offset() + group.size()];
+        let dims = group.dims();
+
+        let ty = dims
+            .iter()
+            .rev()
+            .fold("Score".to_string(), |ty, len| format!("[{ty}; {len}]"));
+        write!(out, "\npub const {}: {ty} = ", group.name()).unwrap();
+        emit_values(&mut out, values, &dims, 0);
+        out.push_str(";\n");
+    }
+
+    out
+}
+
+fn emit_values(out: &mut String, values: &[Score], dims: &[usize], indent: usize) {
+    let Some((_, inner)) = dims.split_first() else {
+        let s = values[0];
+        write!(out, "Score::new({}, {})", s.mg, s.eg).unwrap();
+        return;
+    };
+
+    let stride = inner.iter().product::<usize>();
+    let pad = "    ".repeat(indent + 1);
+
+    out.push_str("[\n");
+    for chunk in values.chunks(stride) {
+        out.push_str(&pad);
+        emit_values(out, chunk, inner, indent + 1);
+        out.push_str(",\n");
+    }
+    out.push_str(&"    ".repeat(indent));
+    out.push(']');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emits_all_params() {
+        let source = emit_rust(&param_values());
+
+        assert!(source.contains("\npub const ISOLATED: Score = Score::new(-8, -12);\n"));
+        assert!(source.contains(
+            "\npub const MATERIAL: [Score; 6] = [\n    Score::new(337, 281),\n    Score::new(365, 297),\n"
+        ));
+        assert!(
+            source.contains("\npub const PST: [[Score; 64]; 6] = [\n    [\n        Score::new(")
+        );
+        assert_eq!(source.matches("pub const").count(), ParamGroup::COUNT);
+        assert_eq!(source.matches("Score::new").count(), PARAM_COUNT);
+    }
+}
@@ -0,0 +1,9 @@
+pub use gunnir_board::*;
+
+pub mod dataset;
+pub mod emit;
+pub mod tuner;
+
+pub use dataset::*;
+pub use emit::*;
+pub use tuner::*;
@@ -0,0 +1,255 @@
+use std::thread;
+
+use crate::*;
+
+/// Midgame and endgame value of a weight while tuning.
+pub type Weight = [f64; 2];
+
+const BETA1: f64 = 0.9;
+const BETA2: f64 = 0.999;
+const EPSILON: f64 = 1e-8;
+
+/// Maps an evaluation in centipawns to an expected game result between 0 and 1.
+#[inline]
+pub fn sigmoid(k: f64, eval: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+}
+
+/// Evaluates an entry with the given weights, like `linear_eval` but without rounding.
+#[inline]
+fn eval(entry: &Entry, weights: &[Weight]) -> f64 {
+    let (mg, eg) = entry.coeffs.iter().fold((0.0, 0.0), |(mg, eg), &(i, c)| {
+        let [w_mg, w_eg] = weights[i as usize];
+        (mg + w_mg * c as f64, eg + w_eg * c as f64)
+    });
+
+    let phase = entry.phase as f64 / MAX_PHASE as f64;
+    mg * phase + eg * (1.0 - phase)
+}
+
+/// Texel tuning: minimizes the mean squared error between the game results of a dataset and
+/// the sigmoid of our evaluation of its positions, using Adam.
+pub struct Tuner {
+    pub dataset: Dataset,
+    pub weights: Vec<Weight>,
+    /// Scales evaluations before the sigmoid, see `fit_k`.
+    pub k: f64,
+    pub learning_rate: f64,
+    pub threads: usize,
+    /// First and second moment estimates of Adam.
+    momentum: Vec<Weight>,
+    velocity: Vec<Weight>,
+    steps: i32,
+}
+
+impl Tuner {
+    /// Starts from the current weights of the engine.
+    pub fn new(dataset: Dataset, threads: usize) -> Self {
+        let weights = param_values()
+            .into_iter()
+            .map(|s| [s.mg as f64, s.eg as f64])
+            .collect();
+
+        Self {
+            dataset,
+            weights,
+            k: 1.0,
+            learning_rate: 1.0,
+            threads: threads.max(1),
+            momentum: vec![[0.0; 2]; PARAM_COUNT],
+            velocity: vec![[0.0; 2]; PARAM_COUNT],
+            steps: 0,
+        }
+    }
+
+    /// Runs `f` over all entries split into one chunk per thread, and returns the results per
+    /// chunk.
+    fn map_chunks<R: Send>(&self, f: impl Fn(&[Entry]) -> R + Sync) -> Vec<R> {
+        let entries = &self.dataset.entries;
+        let chunk_len = entries.len().div_ceil(self.threads).max(1);
+
+        thread::scope(|s| {
+            let handles: Vec<_> = entries
+                .chunks(chunk_len)
+                .map(|chunk| s.spawn(|| f(chunk)))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        })
+    }
+
+    /// The mean squared error of the dataset for the given `k`.
+    pub fn error(&self, k: f64) -> f64 {
+        let sum: f64 = self
+            .map_chunks(|chunk| {
+                chunk
+                    .iter()
+                    .map(|e| (e.result as f64 - sigmoid(k, eval(e, &self.weights))).powi(2))
+                    .sum::<f64>()
+            })
+            .into_iter()
+            .sum();
+
+        sum / self.dataset.entries.len().max(1) as f64
+    }
+
+    /// Finds the `k` that minimizes the error for the current weights by a golden section
+    /// search, and sets it. Returns the new error.
+    pub fn fit_k(&mut self) -> f64 {
+        let ratio = (5f64.sqrt() - 1.0) / 2.0;
+        let (mut lo, mut hi) = (0.0, 10.0);
+
+        while hi - lo > 1e-4 {
+            let a = hi - (hi - lo) * ratio;
+            let b = lo + (hi - lo) * ratio;
+            if self.error(a) < self.error(b) {
+                hi = b;
+            } else {
+                lo = a;
+            }
+        }
+
+        self.k = (lo + hi) / 2.0;
+        self.error(self.k)
+    }
+
+    /// The gradient of the error with respect to every weight.
+    fn gradient(&self) -> Vec<Weight> {
+        let k = self.k;
+        // The derivative of the sigmoid with respect to the evaluation is
+        // `k * ln(10) / 400 * s * (1 - s)`.
+        let scale = k * 10f64.ln() / 400.0;
+
+        let partials = self.map_chunks(|chunk| {
+            let mut gradient = vec![[0.0; 2]; PARAM_COUNT];
+            for entry in chunk {
+                let s = sigmoid(k, eval(entry, &self.weights));
+                let g = 2.0 * (s - entry.result as f64) * scale * s * (1.0 - s);
+
+                let phase = entry.phase as f64 / MAX_PHASE as f64;
+                for &(i, c) in &entry.coeffs {
+                    gradient[i as usize][0] += g * c as f64 * phase;
+                    gradient[i as usize][1] += g * c as f64 * (1.0 - phase);
+                }
+            }
+            gradient
+        });
+
+        let n = self.dataset.entries.len().max(1) as f64;
+        let mut gradient = vec![[0.0; 2]; PARAM_COUNT];
+        for partial in partials {
+            for (g, p) in gradient.iter_mut().zip(partial) {
+                g[0] += p[0] / n;
+                g[1] += p[1] / n;
+            }
+        }
+        gradient
+    }
+
+    /// Takes one step of Adam over the whole dataset.
+    pub fn step(&mut self) {
+        let gradient = self.gradient();
+        self.steps += 1;
+
+        let bias1 = 1.0 - BETA1.powi(self.steps);
+        let bias2 = 1.0 - BETA2.powi(self.steps);
+
+        for (((w, g), m), v) in self
+            .weights
+            .iter_mut()
+            .zip(gradient)
+            .zip(&mut self.momentum)
+            .zip(&mut self.velocity)
+        {
+            for i in 0..2 {
+                m[i] = BETA1 * m[i] + (1.0 - BETA1) * g[i];
+                v[i] = BETA2 * v[i] + (1.0 - BETA2) * g[i] * g[i];
+
+                let m_hat = m[i] / bias1;
+                let v_hat = v[i] / bias2;
+                w[i] -= self.learning_rate * m_hat / (v_hat.sqrt() + EPSILON);
+            }
+        }
+    }
+
+    /// The tuned weights, rounded to integers.
+    pub fn scores(&self) -> Vec<Score> {
+        self.weights
+            .iter()
+            .map(|&[mg, eg]| Score::new(mg.round() as i32, eg.round() as i32))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+    /// Random positions labeled as if a knight was worth much more than we think.
+    fn dataset() -> Dataset {
+        let mut rng = SmallRng::seed_from_u64(0x5eed);
+        let mut weights = param_values();
+        weights[ParamGroup::Material.offset() + PieceType::Knight.idx() as usize] =
+            Score::new(600, 600);
+
+        let mut entries = vec![];
+        for _ in 0..40 {
+            let mut board = Board::start_pos();
+            for _ in 0..60 {
+                let mut moves = vec![];
+                board.gen_moves(|m| moves.extend(m));
+                if moves.is_empty() || board.halfmove_clock >= 99 {
+                    break;
+                }
+                board.make_move(moves[rng.random_range(0..moves.len())]);
+
+                let coeffs = Coefficients::of(&board);
+                let expected = sigmoid(1.0, linear_eval(&coeffs, board.phase(), &weights) as f64);
+                entries.push(Entry::new(&board, expected as f32));
+            }
+        }
+
+        Dataset {
+            entries,
+            skipped: 0,
+        }
+    }
+
+    #[test]
+    fn fits_k() {
+        let mut tuner = Tuner::new(dataset(), 2);
+        let error = tuner.fit_k();
+
+        assert!(tuner.k > 0.0 && tuner.k < 10.0);
+        assert!(error <= tuner.error(tuner.k * 1.1));
+        assert!(error <= tuner.error(tuner.k * 0.9));
+    }
+
+    #[test]
+    fn reduces_error() {
+        let mut tuner = Tuner::new(dataset(), 2);
+        let before = tuner.error(1.0);
+
+        tuner.learning_rate = 5.0;
+        for _ in 0..50 {
+            tuner.step();
+        }
+
+        let knight =
+            tuner.weights[ParamGroup::Material.offset() + PieceType::Knight.idx() as usize];
+        assert!(tuner.error(1.0) < before / 2.0);
+        assert!(knight[0] > MATERIAL[PieceType::Knight.idx() as usize].mg as f64);
+    }
+
+    #[test]
+    fn single_thread_matches() {
+        let mut a = Tuner::new(dataset(), 1);
+        let mut b = Tuner::new(dataset(), 3);
+        a.step();
+        b.step();
+
+        for (a, b) in a.weights.iter().zip(&b.weights) {
+            assert!((a[0] - b[0]).abs() < 1e-9 && (a[1] - b[1]).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::{fs::File, io::BufWriter, process, thread, time::Instant};
+
+use gunnir_datagen::*;
+
+const USAGE: &str = "Usage: gunnir datagen <output> [--games N] [--threads N] [--seed N] [--plies N] [--nodes N] [--960] [--format text|binary]";
+
+fn parse_args(args: &[String]) -> Option<(String, DatagenConfig, OutputFormat)> {
+    let mut out = None;
+    let mut format = OutputFormat::Text;
+    let mut config = DatagenConfig {
+        threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        ..Default::default()
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => config.games = args.next()?.parse().ok()?,
+            "--threads" => config.threads = args.next()?.parse().ok()?,
+            "--seed" => config.seed = args.next()?.parse().ok()?,
+            "--plies" => config.random_plies = args.next()?.parse().ok()?,
+            "--nodes" => config.nodes = args.next()?.parse().ok()?,
+            "--960" => config.chess960 = true,
+            "--format" => {
+                format = match args.next()?.as_str() {
+                    "text" => OutputFormat::Text,
+                    "binary" => OutputFormat::Binary,
+                    _ => return None,
+                }
+            }
+            _ if out.is_none() && !arg.starts_with("--") => out = Some(arg.clone()),
+            _ => return None,
+        }
+    }
+
+    Some((out?, config, format))
+}
+
+/// Generates training data by self-play, see `gunnir_datagen::generate`.
+pub fn run(args: &[String]) {
+    let Some((path, config, format)) = parse_args(args) else {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    };
+
+    let file = File::create(&path).unwrap_or_else(|e| {
+        eprintln!("Failed to create {path}: {e}");
+        process::exit(1);
+    });
+
+    let start = Instant::now();
+    let report = |stats: &DatagenStats| {
+        println!(
+            "{} games, {} positions (+{} ={} -{}) in {:.1}s",
+            stats.games,
+            stats.positions,
+            stats.white_wins,
+            stats.draws,
+            stats.black_wins,
+            start.elapsed().as_secs_f64()
+        );
+    };
+
+    let result = generate(&config, &mut BufWriter::new(file), format, |stats| {
+        if stats.games % 100 == 0 {
+            report(stats);
+        }
+    });
+
+    match result {
+        Ok(stats) => report(&stats),
+        Err(e) => {
+            eprintln!("Failed to write {path}: {e}");
+            process::exit(1);
+        }
+    }
+}
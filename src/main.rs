@@ -1,9 +1,29 @@
+use std::env;
+
 use gunnir_board::*;
-use rand::{rngs::SmallRng, Rng, SeedableRng};
+use rand::{Rng, SeedableRng, rngs::SmallRng};
 use rustyline::{Config, Editor, history::MemHistory};
 
+mod datagen;
+mod perft;
+mod tune;
+
 fn main() {
-    let mut b = Board::read_fen("1n4nr/p1Np2pp/1p6/4pQ2/3P3k/bP1B4/P1P2PPP/R3K2R w KQ - 6 18").unwrap();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("datagen") => datagen::run(&args[1..]),
+        Some("perft") => perft::run(&args[1..], false),
+        Some("divide") => perft::run(&args[1..], true),
+        Some("perft-suite") => perft::run_suite(&args[1..]),
+        Some("tune") => tune::run(&args[1..]),
+        _ => repl(),
+    }
+}
+
+fn repl() {
+    let mut b =
+        Board::read_fen("1n4nr/p1Np2pp/1p6/4pQ2/3P3k/bP1B4/P1P2PPP/R3K2R w KQ - 6 18").unwrap();
     b.print(false);
 
     let mut editor = Editor::<(), MemHistory>::with_history(
@@ -16,6 +36,11 @@ fn main() {
 
     'outer: while let Ok(line) = editor.readline("") {
         for s in line.trim().split_ascii_whitespace() {
+            if s == "eval" {
+                println!("{}", evaluate_traced(&b).1);
+                continue;
+            }
+
             let Some(mv) = b.parse_move(s.trim(), false) else {
                 eprintln!("Invalid move!");
                 continue;
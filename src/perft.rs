@@ -0,0 +1,217 @@
+use std::{fs, process, str::FromStr, thread, time::Instant};
+
+use gunnir_board::*;
+
+const USAGE: &str = "\
+Usage: gunnir perft <depth> [startpos|<fen>] [moves...] [--960] [--threads N [--split 1|2] | --hash MB [--verify]]
+       gunnir divide <depth> [startpos|<fen>] [moves...] [--960]";
+
+struct Options {
+    depth: u8,
+    board: Board,
+    chess960: bool,
+    threads: usize,
+    split_depth: u8,
+    hash_mb: Option<usize>,
+    verify: bool,
+}
+
+/// Whether `arg` is shaped like a move in long algebraic notation, which tells the moves apart
+/// from the fields of an unquoted FEN.
+fn looks_like_move(arg: &str) -> bool {
+    let b = arg.as_bytes();
+    (4..=5).contains(&b.len())
+        && matches!(b[0], b'a'..=b'h')
+        && matches!(b[1], b'1'..=b'8')
+        && matches!(b[2], b'a'..=b'h')
+        && matches!(b[3], b'1'..=b'8')
+}
+
+fn parse_value<T: FromStr>(arg: Option<&String>) -> Result<T, &'static str> {
+    arg.and_then(|arg| arg.parse().ok()).ok_or(USAGE)
+}
+
+fn parse_args(args: &[String], divide: bool) -> Result<Options, String> {
+    let mut chess960 = false;
+    let mut threads = None;
+    let mut split_depth = None;
+    let mut hash_mb = None;
+    let mut verify = false;
+    let mut positional = vec![];
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--960" => chess960 = true,
+            "--threads" => threads = Some(parse_value(iter.next())?),
+            "--split" => split_depth = Some(parse_value(iter.next())?),
+            "--hash" => hash_mb = Some(parse_value(iter.next())?),
+            "--verify" => verify = true,
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    // Each way of counting supports only its own options, so don't silently ignore the rest.
+    let parallel = threads.is_some() || split_depth.is_some();
+    let hashed = hash_mb.is_some() || verify;
+    if (divide && (parallel || hashed))
+        || (parallel && hashed)
+        || (split_depth.is_some() && threads.is_none())
+    {
+        return Err(USAGE.to_string());
+    }
+
+    let mut args = positional.into_iter().peekable();
+    let depth = args.next().and_then(|arg| arg.parse().ok()).ok_or(USAGE)?;
+
+    let mut board = Board::start_pos();
+    match args.peek() {
+        Some(&"startpos") => {
+            args.next();
+        }
+        Some(arg) if arg.contains('/') => {
+            // The FEN may be passed as a single argument, or as one argument per field.
+            let mut fields: Vec<_> = args.next().unwrap().split_whitespace().collect();
+            while fields.len() < 6
+                && let Some(&field) = args.peek()
+                && !looks_like_move(field)
+            {
+                fields.push(field);
+                args.next();
+            }
+
+            let fen = fields.join(" ");
+            board = Board::read_fen(&fen).ok_or(format!("Invalid FEN: {fen}"))?;
+        }
+        _ => {}
+    }
+
+    for lan in args {
+        let mov = board
+            .parse_move(lan, chess960)
+            .filter(|&mov| {
+                let mut legal = false;
+                board.gen_moves(|moves| legal |= moves.into_iter().any(|m| m == mov));
+                legal
+            })
+            .ok_or(format!("Illegal move: {lan}"))?;
+        board.make_move(mov);
+    }
+
+    Ok(Options {
+        depth,
+        board,
+        chess960,
+        threads: threads.unwrap_or(1),
+        split_depth: split_depth.unwrap_or(1),
+        hash_mb,
+        verify,
+    })
+}
+
+/// Counts the leaf nodes of the move tree. With `divide`, the count below every legal move is
+/// printed too. `--threads` counts on several threads, splitting the tree `--split` plies
+/// below the root. `--hash` caches counts in a table of the given size instead, and `--verify`
+/// checks every count taken from it. These options can't be combined with each other across
+/// the two groups, or with `divide`.
+pub fn run(args: &[String], divide: bool) {
+    let options = parse_args(args, divide).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(1);
+    });
+    let board = &options.board;
+
+    let start = Instant::now();
+    let nodes = if divide {
+        let mut counts: Vec<_> = gunnir_board::divide(board, options.depth)
+            .into_iter()
+            .map(|(mov, nodes)| (board.move_to_uci(mov, options.chess960), nodes))
+            .collect();
+        counts.sort();
+
+        for (lan, nodes) in &counts {
+            println!("{lan}: {nodes}");
+        }
+        println!();
+
+        counts.iter().map(|&(_, nodes)| nodes).sum()
+    } else if options.verify {
+        let mut table = PerftTable::new(options.hash_mb.unwrap_or(16));
+        verify_hashed_perft(board, options.depth, &mut table).unwrap_or_else(|e| {
+            eprintln!(
+                "Hash mismatch at depth {} in {}: cached {} nodes, counted {}",
+                e.depth, e.fen, e.cached, e.actual
+            );
+            process::exit(1);
+        })
+    } else if let Some(mb) = options.hash_mb {
+        hashed_perft(board, options.depth, &mut PerftTable::new(mb))
+    } else if options.threads > 1 {
+        let result = parallel_perft(board, options.depth, options.threads, options.split_depth);
+        for (i, stats) in result.threads.iter().enumerate() {
+            println!(
+                "Thread {i}: {} tasks, {} nodes, {:.3}s",
+                stats.tasks,
+                stats.nodes,
+                stats.busy.as_secs_f64()
+            );
+        }
+        println!();
+
+        result.nodes
+    } else {
+        perft(board, options.depth)
+    };
+    let elapsed = start.elapsed().as_secs_f64();
+
+    println!("Nodes: {nodes}");
+    println!("Time: {:.3}s", elapsed);
+    println!("NPS: {:.0}", nodes as f64 / elapsed.max(1e-9));
+}
+
+const SUITE_USAGE: &str = "Usage: gunnir perft-suite <file> [--depth N] [--nodes N] [--threads N]";
+
+fn parse_suite_args(args: &[String]) -> Option<(String, SuiteLimits)> {
+    let mut path = None;
+    let mut limits = SuiteLimits {
+        threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        ..Default::default()
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--depth" => limits.max_depth = args.next()?.parse().ok()?,
+            "--nodes" => limits.max_nodes = args.next()?.parse().ok()?,
+            "--threads" => limits.threads = args.next()?.parse().ok()?,
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg.clone()),
+            _ => return None,
+        }
+    }
+
+    Some((path?, limits))
+}
+
+/// Runs a suite of perft positions, see `gunnir_board::perft_suite`, and exits with an error if
+/// any count is wrong.
+pub fn run_suite(args: &[String]) {
+    let Some((path, limits)) = parse_suite_args(args) else {
+        eprintln!("{SUITE_USAGE}");
+        process::exit(1);
+    };
+
+    let text = fs::read_to_string(&path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {path}: {e}");
+        process::exit(1);
+    });
+    let cases = parse_perft_suite(&text).unwrap_or_else(|line| {
+        eprintln!("Invalid perft case on line {line} of {path}");
+        process::exit(1);
+    });
+
+    let results = run_perft_suite(&cases, &limits);
+    print!("{}", format_suite_report(&results));
+    if !results.iter().all(CaseResult::passed) {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,88 @@
+use std::{fs, process, thread, time::Instant};
+
+use gunnir_tune::*;
+
+const USAGE: &str =
+    "Usage: gunnir tune <dataset> [--epochs N] [--lr RATE] [--threads N] [--out FILE]";
+
+struct Options {
+    dataset: String,
+    epochs: usize,
+    learning_rate: f64,
+    threads: usize,
+    out: String,
+}
+
+fn parse_args(args: &[String]) -> Option<Options> {
+    let mut options = Options {
+        dataset: String::new(),
+        epochs: 1000,
+        learning_rate: 1.0,
+        threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        out: "tuned_params.rs".to_string(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--epochs" => options.epochs = args.next()?.parse().ok()?,
+            "--lr" => options.learning_rate = args.next()?.parse().ok()?,
+            "--threads" => options.threads = args.next()?.parse().ok()?,
+            "--out" => options.out = args.next()?.clone(),
+            _ if options.dataset.is_empty() && !arg.starts_with("--") => {
+                options.dataset = arg.clone()
+            }
+            _ => return None,
+        }
+    }
+
+    (!options.dataset.is_empty()).then_some(options)
+}
+
+/// Tunes the evaluation weights on a dataset of labeled positions, and writes them as Rust
+/// source.
+pub fn run(args: &[String]) {
+    let Some(options) = parse_args(args) else {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    };
+
+    let text = fs::read_to_string(&options.dataset).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {e}", options.dataset);
+        process::exit(1);
+    });
+
+    let start = Instant::now();
+    let dataset = Dataset::parse(&text, options.threads);
+    drop(text);
+    println!(
+        "Loaded {} positions ({} invalid lines skipped) in {:.1}s",
+        dataset.entries.len(),
+        dataset.skipped,
+        start.elapsed().as_secs_f64()
+    );
+
+    let mut tuner = Tuner::new(dataset, options.threads);
+    tuner.learning_rate = options.learning_rate;
+
+    let error = tuner.fit_k();
+    println!("K = {:.4}, initial error {error:.6}", tuner.k);
+
+    for epoch in 1..=options.epochs {
+        tuner.step();
+
+        if epoch % 50 == 0 || epoch == options.epochs {
+            println!(
+                "Epoch {epoch}: error {:.6} ({:.1}s)",
+                tuner.error(tuner.k),
+                start.elapsed().as_secs_f64()
+            );
+            if let Err(e) = fs::write(&options.out, emit_rust(&tuner.scores())) {
+                eprintln!("Failed to write {}: {e}", options.out);
+                process::exit(1);
+            }
+        }
+    }
+
+    println!("Wrote tuned weights to {}", options.out);
+}